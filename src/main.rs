@@ -1,5 +1,5 @@
 use clap::Parser;
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
 use thiserror::Error;
 
 mod y2024;
@@ -20,6 +20,103 @@ pub struct Args {
     ///verbosity
     #[arg(long, short, default_value = "1")]
     verbosity: u8,
+    ///day specific parameters as key=value (a bare key is a flag)
+    #[arg(long = "param", short = 'p', value_parser = parse_param)]
+    params: Vec<(String, String)>,
+}
+
+fn parse_param(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.is_empty() => Ok((k.into(), v.into())),
+        Some(_) => Err(format!("missing key in {s}")),
+        None => Ok((s.into(), "true".into())),
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Params {
+    values: HashMap<String, String>,
+    /// every key the selected day reads
+    keys: &'static [&'static str],
+}
+
+impl Params {
+    /// Rejects keys the day does not read, e.g. typos or keys of another day, before it runs.
+    fn new(values: Vec<(String, String)>, keys: &'static [&'static str]) -> Result<Self> {
+        let mut unknown = values
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .filter(|k| !keys.contains(k))
+            .collect::<Vec<&str>>();
+        if !unknown.is_empty() {
+            unknown.sort();
+            unknown.dedup();
+            let expected = if keys.is_empty() {
+                "this day takes no parameters".to_string()
+            } else {
+                format!("this day accepts {}", keys.join(", "))
+            };
+            return Err(AOCError::ParseError(format!(
+                "unknown parameters {}, {expected}",
+                unknown.join(", ")
+            )));
+        }
+        Ok(Self {
+            values: values.into_iter().collect(),
+            keys,
+        })
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<Option<T>> {
+        debug_assert!(
+            self.keys.contains(&key),
+            "{key} is missing from the day's PARAMS"
+        );
+        self.values
+            .get(key)
+            .map(|v| {
+                v.parse::<T>()
+                    .map_err(|_e| AOCError::ParseError(format!("invalid value {v} for {key}")))
+            })
+            .transpose()
+    }
+
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T> {
+        Ok(self.get(key)?.unwrap_or(default))
+    }
+
+    pub fn flag(&self, key: &str) -> Result<bool> {
+        self.get_or(key, false)
+    }
+}
+
+/// Parameter keys of every day that takes any.
+fn day_params(year: u16, day: u8) -> &'static [&'static str] {
+    match (year, day) {
+        (2024, 1) => y2024::day1::PARAMS,
+        (2024, 2) => y2024::day2::PARAMS,
+        (2024, 3) => y2024::day3::PARAMS,
+        (2024, 4) => y2024::day4::PARAMS,
+        (2024, 5) => y2024::day5::PARAMS,
+        (2024, 6) => y2024::day6::PARAMS,
+        (2024, 7) => y2024::day7::PARAMS,
+        (2024, 8) => y2024::day8::PARAMS,
+        (2024, 9) => y2024::day9::PARAMS,
+        (2024, 10) => y2024::day10::PARAMS,
+        (2024, 11) => y2024::day11::PARAMS,
+        (2024, 12) => y2024::day12::PARAMS,
+        (2024, 13) => y2024::day13::PARAMS,
+        (2024, 14) => y2024::day14::PARAMS,
+        (2024, 15) => y2024::day15::PARAMS,
+        (2024, 16) => y2024::day16::PARAMS,
+        (2024, 17) => y2024::day17::PARAMS,
+        (2024, 18) => y2024::day18::PARAMS,
+        (2024, 19) => y2024::day19::PARAMS,
+        (2024, 20) => y2024::day20::PARAMS,
+        (2024, 25) => y2024::day25::PARAMS,
+        (2025, 1) => y2025::day1::PARAMS,
+        _ => &[],
+    }
 }
 
 pub type Result<T> = std::result::Result<T, AOCError>;
@@ -37,37 +134,52 @@ pub enum AOCError {
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let params = Params::new(args.params, day_params(args.year, args.day))?;
     match (args.year, args.day) {
-        (2024, 1) => y2024::day1::_main(args.data, args.verbosity),
-        (2024, 2) => y2024::day2::_main(args.data, args.verbosity),
-        (2024, 3) => y2024::day3::_main(args.data, args.verbosity),
-        (2024, 4) => y2024::day4::_main(args.data, args.verbosity),
-        (2024, 5) => y2024::day5::_main(args.data, args.verbosity),
-        (2024, 6) => y2024::day6::_main(args.data, args.verbosity),
-        (2024, 7) => y2024::day7::_main(args.data, args.verbosity),
-        (2024, 8) => y2024::day8::_main(args.data, args.verbosity),
-        (2024, 9) => y2024::day9::_main(args.data, args.verbosity),
-        (2024, 10) => y2024::day10::_main(args.data, args.verbosity),
-        (2024, 11) => y2024::day11::_main(args.data, args.verbosity),
-        (2024, 12) => y2024::day12::_main(args.data, args.verbosity),
-        (2024, 13) => y2024::day13::_main(args.data, args.verbosity),
-        (2024, 14) => y2024::day14::_main(args.data, args.verbosity),
-        (2024, 15) => y2024::day15::_main(args.data, args.verbosity),
-        (2024, 16) => y2024::day16::_main(args.data, args.verbosity),
-        (2024, 17) => y2024::day17::_main(args.data, args.verbosity),
-        (2024, 18) => y2024::day18::_main(args.data, args.verbosity),
-        (2024, 19) => y2024::day19::_main(args.data, args.verbosity),
-        (2024, 20) => y2024::day20::_main(args.data, args.verbosity),
+        (2024, 1) => y2024::day1::_main(args.data, args.verbosity, &params),
+        (2024, 2) => y2024::day2::_main(args.data, args.verbosity, &params),
+        (2024, 3) => y2024::day3::_main(args.data, args.verbosity, &params),
+        (2024, 4) => y2024::day4::_main(args.data, args.verbosity, &params),
+        (2024, 5) => y2024::day5::_main(args.data, args.verbosity, &params),
+        (2024, 6) => y2024::day6::_main(args.data, args.verbosity, &params),
+        (2024, 7) => y2024::day7::_main(args.data, args.verbosity, &params),
+        (2024, 8) => y2024::day8::_main(args.data, args.verbosity, &params),
+        (2024, 9) => y2024::day9::_main(args.data, args.verbosity, &params),
+        (2024, 10) => y2024::day10::_main(args.data, args.verbosity, &params),
+        (2024, 11) => y2024::day11::_main(args.data, args.verbosity, &params),
+        (2024, 12) => y2024::day12::_main(args.data, args.verbosity, &params),
+        (2024, 13) => y2024::day13::_main(args.data, args.verbosity, &params),
+        (2024, 14) => y2024::day14::_main(args.data, args.verbosity, &params),
+        (2024, 15) => y2024::day15::_main(args.data, args.verbosity, &params),
+        (2024, 16) => y2024::day16::_main(args.data, args.verbosity, &params),
+        (2024, 17) => y2024::day17::_main(args.data, args.verbosity, &params),
+        (2024, 18) => y2024::day18::_main(args.data, args.verbosity, &params),
+        (2024, 19) => y2024::day19::_main(args.data, args.verbosity, &params),
+        (2024, 20) => y2024::day20::_main(args.data, args.verbosity, &params),
         (2024, 21) => y2024::day21::_main(args.data, args.verbosity),
         (2024, 22) => y2024::day22::_main(args.data, args.verbosity),
         (2024, 23) => y2024::day23::_main(args.data, args.verbosity),
         (2024, 24) => y2024::day24::_main(args.data, args.verbosity),
-        (2024, 25) => y2024::day25::_main(args.data, args.verbosity),
-        (2025, 1) => y2025::day1::_main(args.data, args.verbosity),
+        (2024, 25) => y2024::day25::_main(args.data, args.verbosity, &params),
+        (2025, 1) => y2025::day1::_main(args.data, args.verbosity, &params),
         (2025, 2) => y2025::day2::_main(args.data, args.verbosity),
         (2025, 3) => y2025::day3::_main(args.data, args.verbosity),
         (2025, 4) => y2025::day4::_main(args.data, args.verbosity),
         _ => Err(AOCError::GenError("Not implemented".into())),
-    }?;
-    Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_params() {
+        let values = vec![("top".to_string(), "2".to_string())];
+        let params = Params::new(values.clone(), &["stats", "top"]).unwrap();
+        assert_eq!(params.get_or("top", 3).unwrap(), 2);
+        assert!(!params.flag("stats").unwrap());
+        assert!(Params::new(values.clone(), &["stats"]).is_err());
+        assert!(Params::new(values, &[]).is_err());
+    }
 }
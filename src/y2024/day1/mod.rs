@@ -1,66 +1,169 @@
-use super::{AOCError, Result};
-use std::collections::HashMap;
+use super::{AOCError, Params, Result};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::iter::repeat_n;
 use std::path::PathBuf;
 
-type Array = (Vec<u64>, Vec<u64>);
+pub const PARAMS: &[&str] = &["stats", "top", "csv"];
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
-    let data = load_data(data)?;
-    let res2 = calc_similarity(&data);
-    let res = get_diff(data);
-    write_data((res, res2))?;
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
+    let columns = Columns::from_reader(BufReader::new(fs::File::open(data)?))?;
+    if params.flag("stats")? {
+        print_stats(&columns, params.get_or("top", 3)?);
+    }
+    if let Some(out) = params.get::<PathBuf>("csv")? {
+        write_csv(&columns, out)?;
+    }
+    println!(
+        "res1: {}, res2: {}",
+        columns.distance(),
+        columns.similarity()
+    );
     Ok(())
 }
 
-fn get_diff(data: Array) -> u64 {
-    let mut v1 = data.0;
-    v1.sort();
-    let mut v2 = data.1;
-    v2.sort();
-    v1.iter()
-        .zip(v2.iter())
-        .map(|(val1, val2)| val1.abs_diff(*val2))
-        .sum()
+/// Occurrence count of every ID, kept in sorted order.
+type Counts = BTreeMap<u64, u64>;
+
+/// Two columns of IDs, stored as counting maps so their length does not matter.
+#[derive(Debug, Default)]
+struct Columns {
+    left: Counts,
+    right: Counts,
 }
 
-fn calc_similarity(data: &Array) -> u64 {
-    let mut map: HashMap<u64, u64> = HashMap::default();
-    data.0
-        .iter()
-        .map(|value| {
-            let key = map
-                .entry(*value)
-                .or_insert(data.1.iter().filter(|&v| v == value).count() as u64);
-            *value * *key
-        })
-        .sum()
+impl Columns {
+    fn from_reader(reader: impl BufRead) -> Result<Self> {
+        let mut columns = Columns::default();
+        for line in reader.lines() {
+            let line = line?;
+            let mut items = line.split_whitespace().map(|item| {
+                item.parse::<u64>()
+                    .map_err(|_e| AOCError::ParseError(format!("could not parse {}", item)))
+            });
+            match (items.next(), items.next()) {
+                (Some(v1), Some(v2)) => {
+                    *columns.left.entry(v1?).or_default() += 1;
+                    *columns.right.entry(v2?).or_default() += 1;
+                }
+                (None, None) => continue,
+                _ => {
+                    return Err(AOCError::ParseError(
+                        "a line had less than 2 entries".into(),
+                    ));
+                }
+            }
+        }
+        Ok(columns)
+    }
+
+    /// The smallest left ID paired with the smallest right ID and so on, merged from both
+    /// sorted columns.
+    fn pairs<'a>(&'a self) -> impl Iterator<Item = (u64, u64)> + 'a {
+        let sorted = |counts: &'a Counts| {
+            counts
+                .iter()
+                .flat_map(|(id, count)| repeat_n(*id, *count as usize))
+        };
+        sorted(&self.left).zip(sorted(&self.right))
+    }
+
+    fn distance(&self) -> u64 {
+        self.pairs().map(|(l, r)| l.abs_diff(r)).sum()
+    }
+
+    fn similarity(&self) -> u64 {
+        self.left
+            .iter()
+            .map(|(id, count)| id * count * self.right.get(id).unwrap_or(&0))
+            .sum()
+    }
+
+    /// IDs of `counts` that never appear in `other`, with how often they occur.
+    fn unmatched<'a>(
+        counts: &'a Counts,
+        other: &'a Counts,
+    ) -> impl Iterator<Item = (&'a u64, &'a u64)> {
+        counts.iter().filter(|(id, _)| !other.contains_key(id))
+    }
+}
+
+/// The `n` most frequent IDs, most frequent first and smaller IDs first on ties.
+fn most_frequent(counts: &Counts, n: usize) -> Vec<(u64, u64)> {
+    let mut frequent = counts.iter().map(|(id, c)| (*id, *c)).collect::<Vec<_>>();
+    frequent.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    frequent.truncate(n);
+    frequent
 }
 
-fn load_data(data: PathBuf) -> Result<Array> {
-    let f = fs::read_to_string(data)?;
-    let mut lines = Array::default();
-    for line in f.lines() {
-        let l = line
-            .split_whitespace()
-            .map(|item| match item.parse::<u64>() {
-                Ok(v) => Ok(v),
-                Err(_e) => Err(AOCError::ParseError(format!("could not parse {}", item))),
-            })
-            .collect::<Result<Vec<u64>>>()?;
-        if let [v1, v2, ..] = l[..] {
-            lines.0.push(v1);
-            lines.1.push(v2);
-        } else {
-            return Err(AOCError::ParseError(
-                "a line had less than 2 entries".into(),
-            ));
+fn print_stats(columns: &Columns, top: usize) {
+    for (name, counts, other) in [
+        ("left", &columns.left, &columns.right),
+        ("right", &columns.right, &columns.left),
+    ] {
+        let unmatched = Columns::unmatched(counts, other).collect::<Vec<_>>();
+        println!(
+            "{name}: {} IDs, {} distinct, {} distinct not in the other column ({} occurrences)",
+            counts.values().sum::<u64>(),
+            counts.len(),
+            unmatched.len(),
+            unmatched.iter().map(|(_, c)| **c).sum::<u64>()
+        );
+        let mut listed = unmatched
+            .iter()
+            .take(top)
+            .map(|(id, c)| format!("{id} x{c}"))
+            .collect::<Vec<String>>();
+        if unmatched.len() > top {
+            listed.push(format!("and {} more", unmatched.len() - top));
+        } else if listed.is_empty() {
+            listed.push("none".into());
         }
+        println!("{name} not in the other column: {}", listed.join(", "));
+        let frequent = most_frequent(counts, top)
+            .iter()
+            .map(|(id, c)| format!("{id} x{c}"))
+            .collect::<Vec<String>>();
+        println!("{name} most frequent: {}", frequent.join(", "));
     }
-    Ok(lines)
 }
 
-fn write_data(data: (u64, u64)) -> Result<()> {
-    println!("res1: {}, res2: {}", data.0, data.1);
+fn write_csv(columns: &Columns, out: PathBuf) -> Result<()> {
+    let mut w = BufWriter::new(fs::File::create(out)?);
+    writeln!(w, "left,right,distance")?;
+    for (l, r) in columns.pairs() {
+        writeln!(w, "{l},{r},{}", l.abs_diff(r))?;
+    }
+    w.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n";
+
+    #[test]
+    fn example() {
+        let columns = Columns::from_reader(EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(
+            columns.pairs().collect::<Vec<_>>(),
+            vec![(1, 3), (2, 3), (3, 3), (3, 4), (3, 5), (4, 9)]
+        );
+        assert_eq!(columns.distance(), 11);
+        assert_eq!(columns.similarity(), 31);
+    }
+
+    #[test]
+    fn stats() {
+        let columns = Columns::from_reader(EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(
+            Columns::unmatched(&columns.left, &columns.right).collect::<Vec<_>>(),
+            vec![(&1, &1), (&2, &1)]
+        );
+        assert_eq!(most_frequent(&columns.right, 2), vec![(3, 3), (4, 1)]);
+        assert!(Columns::from_reader("1 2\n3\n".as_bytes()).is_err());
+    }
+}
@@ -1,16 +1,49 @@
-use super::Result;
-use std::{collections::HashSet, path::PathBuf};
+use super::{AOCError, Params, Result};
+use std::{fs, path::PathBuf};
 
-type HeightMap = Vec<Vec<u8>>;
+/// `None` marks impassable cells.
+type HeightMap = Vec<Vec<Option<u8>>>;
 type Position = (usize, usize);
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["trailhead", "export"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
     let height_map = parse(data)?;
     if verbosity > 2 {
         print_heightmap(&height_map);
     }
-    let res = solve(&height_map);
+    let trails = Trails::new(&height_map);
+    let res = trails.solve(&height_map);
     println!("res1: {}, res2: {}", res.0, res.1);
+    if let Some(start) = params.get::<String>("trailhead")? {
+        let start = start
+            .split_once(',')
+            .and_then(|(row, col)| Some((row.parse().ok()?, col.parse().ok()?)))
+            .ok_or(AOCError::ParseError(format!(
+                "invalid trailhead {start}, expected row,col"
+            )))?;
+        if height(&height_map, start) != Some(0) {
+            return Err(AOCError::GenError(format!(
+                "{:?} is not a trailhead",
+                start
+            )));
+        }
+        let paths = trails
+            .enumerate(&height_map, start)
+            .iter()
+            .map(|trail| {
+                trail
+                    .iter()
+                    .map(|(i, j)| format!("{i},{j}"))
+                    .collect::<Vec<String>>()
+                    .join(" -> ")
+            })
+            .collect::<Vec<String>>();
+        match params.get::<PathBuf>("export")? {
+            Some(out) => fs::write(out, paths.join("\n") + "\n")?,
+            None => paths.iter().for_each(|trail| println!("{trail}")),
+        }
+    }
     Ok(())
 }
 
@@ -18,73 +51,171 @@ fn print_heightmap(heights: &HeightMap) {
     for row in heights {
         println!();
         for height in row {
-            print!("{}", height);
+            match height {
+                Some(h) => print!("{}", h),
+                None => print!("."),
+            }
         }
     }
     println!();
 }
 
 fn parse(data: PathBuf) -> Result<HeightMap> {
-    let f: Vec<u8> = std::fs::read(data)?;
-    Ok(f.split(|item| *item == b'\n')
+    let f = fs::read_to_string(data)?;
+    parse_str(&f)
+}
+
+fn parse_str(f: &str) -> Result<HeightMap> {
+    f.lines()
         .filter(|row| !row.is_empty())
-        .map(|row| row.iter().map(|c| c - 48).collect::<Vec<u8>>())
-        .collect::<HeightMap>())
+        .map(|row| {
+            row.chars()
+                .map(|c| match c {
+                    '.' => Ok(None),
+                    _ => c
+                        .to_digit(10)
+                        .map(|h| Some(h as u8))
+                        .ok_or(AOCError::ParseError(format!("invalid height {c}"))),
+                })
+                .collect::<Result<Vec<Option<u8>>>>()
+        })
+        .collect::<Result<HeightMap>>()
+}
+
+fn height(heights: &HeightMap, pos: Position) -> Option<u8> {
+    *heights.get(pos.0)?.get(pos.1)?
 }
 
 fn next_path(heights: &HeightMap, start: Position) -> Vec<Position> {
-    let current_height = heights[start.0][start.1];
+    let Some(current_height) = height(heights, start) else {
+        return Vec::new();
+    };
     let mut next_fields = Vec::new();
-    if start.0 > 0 && heights[start.0 - 1][start.1] == current_height + 1 {
-        next_fields.push((start.0 - 1, start.1));
-    }
-    if start.0 < heights.len() - 1 && heights[start.0 + 1][start.1] == current_height + 1 {
-        next_fields.push((start.0 + 1, start.1));
-    }
-    if start.1 > 0 && heights[start.0][start.1 - 1] == current_height + 1 {
-        next_fields.push((start.0, start.1 - 1));
-    }
-
-    if start.1 < heights[0].len() - 1 && heights[start.0][start.1 + 1] == current_height + 1 {
-        next_fields.push((start.0, start.1 + 1));
+    for (di, dj) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        let (Some(i), Some(j)) = (
+            start.0.checked_add_signed(di),
+            start.1.checked_add_signed(dj),
+        ) else {
+            continue;
+        };
+        if height(heights, (i, j)) == Some(current_height + 1) {
+            next_fields.push((i, j));
+        }
     }
     next_fields
 }
 
-fn get_paths_unique(heights: &HeightMap, start: Position) -> Vec<Position> {
-    if heights[start.0][start.1] == 9 {
-        return vec![start];
-    }
-    let mut tot = HashSet::new();
-    for next_start in next_path(heights, start) {
-        for p in get_paths_unique(heights, next_start) {
-            tot.insert(p);
+/// Per cell results of a dp over the heights from 9 down to 0.
+struct Trails {
+    /// number of distinct trails from the cell to any 9
+    rating: Vec<Vec<usize>>,
+    /// bitset over the 9s reachable from the cell
+    peaks: Vec<Vec<Vec<u64>>>,
+}
+
+impl Trails {
+    fn new(heights: &HeightMap) -> Self {
+        let mut by_height: [Vec<Position>; 10] = Default::default();
+        for (i, row) in heights.iter().enumerate() {
+            for (j, h) in row.iter().enumerate() {
+                if let Some(h) = h {
+                    by_height[*h as usize].push((i, j));
+                }
+            }
+        }
+        let words = by_height[9].len().div_ceil(64);
+        let mut trails = Trails {
+            rating: heights.iter().map(|row| vec![0; row.len()]).collect(),
+            peaks: heights
+                .iter()
+                .map(|row| vec![Vec::new(); row.len()])
+                .collect(),
+        };
+        for (n, (i, j)) in by_height[9].iter().enumerate() {
+            trails.rating[*i][*j] = 1;
+            trails.peaks[*i][*j] = vec![0; words];
+            trails.peaks[*i][*j][n / 64] |= 1 << (n % 64);
+        }
+        for level in by_height[..9].iter().rev() {
+            for (i, j) in level {
+                let mut peaks = vec![0; words];
+                let mut rating = 0;
+                for (ni, nj) in next_path(heights, (*i, *j)) {
+                    rating += trails.rating[ni][nj];
+                    for (w, n) in peaks.iter_mut().zip(&trails.peaks[ni][nj]) {
+                        *w |= n;
+                    }
+                }
+                trails.rating[*i][*j] = rating;
+                trails.peaks[*i][*j] = peaks;
+            }
         }
+        trails
     }
-    tot.into_iter().collect::<Vec<Position>>()
-}
 
-fn get_paths(heights: &HeightMap, start: Position) -> usize {
-    if heights[start.0][start.1] == 9 {
-        return 1;
+    fn score(&self, pos: Position) -> usize {
+        self.peaks[pos.0][pos.1]
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum()
     }
-    let mut tot = 0;
-    for next_start in next_path(heights, start) {
-        tot += get_paths(heights, next_start);
+
+    fn solve(&self, heights: &HeightMap) -> (usize, usize) {
+        let mut tot = 0;
+        let mut tot2 = 0;
+        for (i, row) in heights.iter().enumerate() {
+            for (j, height) in row.iter().enumerate() {
+                if *height == Some(0) {
+                    tot += self.score((i, j));
+                    tot2 += self.rating[i][j];
+                }
+            }
+        }
+        (tot, tot2)
     }
-    tot
-}
 
-fn solve(heights: &HeightMap) -> (usize, usize) {
-    let mut tot = 0;
-    let mut tot2 = 0;
-    for (i, row) in heights.iter().enumerate() {
-        for (j, height) in row.iter().enumerate() {
-            if *height == 0 {
-                tot += get_paths_unique(heights, (i, j)).len();
-                tot2 += get_paths(heights, (i, j));
+    /// Every distinct trail from `start` to a 9, only following cells that lead to one.
+    fn enumerate(&self, heights: &HeightMap, start: Position) -> Vec<Vec<Position>> {
+        let mut trails = Vec::new();
+        let mut stack = vec![vec![start]];
+        while let Some(trail) = stack.pop() {
+            let last = trail[trail.len() - 1];
+            if height(heights, last) == Some(9) {
+                trails.push(trail);
+                continue;
+            }
+            for next in next_path(heights, last) {
+                if self.rating[next.0][next.1] > 0 {
+                    let mut next_trail = trail.clone();
+                    next_trail.push(next);
+                    stack.push(next_trail);
+                }
             }
         }
+        trails.reverse();
+        trails
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let heights = parse_str(
+            "89010123\n78121874\n87430965\n96549874\n45678903\n32019012\n01329801\n10456732\n",
+        )
+        .unwrap();
+        assert_eq!(Trails::new(&heights).solve(&heights), (36, 81));
+    }
+
+    #[test]
+    fn impassable() {
+        let heights =
+            parse_str(".....0.\n..4321.\n..5..2.\n..6543.\n..7..4.\n..8765.\n..9....\n").unwrap();
+        let trails = Trails::new(&heights);
+        assert_eq!(trails.solve(&heights), (1, 3));
+        assert_eq!(trails.enumerate(&heights, (0, 5)).len(), 3);
     }
-    (tot, tot2)
 }
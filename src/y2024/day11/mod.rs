@@ -1,17 +1,54 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
-    let stones2 = parse_hash(&data)?;
-    let res2 = solve_efficient(stones2, 75);
+pub const PARAMS: &[&str] = &["blinks", "stats"];
+
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
     let stones = parse_hash(&data)?;
-    let res = solve_efficient(stones, 25);
-    println!("res: {}, res2: {}", res, res2);
+    let blinks = params.get::<usize>("blinks")?;
+    let stats = evolve(stones, blinks.unwrap_or(0).max(75));
+    if params.flag("stats")? {
+        println!("{:>6} | {:>8} | {:>40}", "blink", "distinct", "total");
+        for (blink, s) in stats.iter().enumerate() {
+            println!("{:>6} | {:>8} | {:>40}", blink, s.distinct, s.total);
+        }
+    }
+    if let Some(stable) = stats.iter().position(|s| s.is_stable) {
+        println!("distinct stones stop changing after {stable} blinks");
+    }
+    let total = |blinks: usize| match stats.get(blinks) {
+        Some(s) => s.total.to_string(),
+        None => format!("overflow (counts fit until blink {})", stats.len() - 1),
+    };
+    println!("res: {}, res2: {}", total(25), total(75));
+    if let Some(blinks) = blinks {
+        println!("after {} blinks: {}", blinks, total(blinks));
+    }
     Ok(())
 }
 
-type HashStones = HashMap<u64, usize>;
+type HashStones = HashMap<u64, u128>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BlinkStats {
+    distinct: usize,
+    total: u128,
+    /// the set of distinct values is the same as after the previous blink, so it never changes again
+    is_stable: bool,
+}
+
+impl BlinkStats {
+    fn new(stones: &HashStones, previous: Option<&HashStones>) -> Self {
+        Self {
+            distinct: stones.len(),
+            total: stones.values().sum(),
+            is_stable: previous.is_some_and(|previous| {
+                previous.len() == stones.len() && previous.keys().all(|k| stones.contains_key(k))
+            }),
+        }
+    }
+}
 
 fn parse_hash(data: &PathBuf) -> Result<HashStones> {
     let f = std::fs::read_to_string(data)?;
@@ -20,54 +57,63 @@ fn parse_hash(data: &PathBuf) -> Result<HashStones> {
         num.parse::<u64>()
             .map_err(|_e| AOCError::ParseError("could not parse num".into()))
     }) {
-        stones.entry(stone?).and_modify(|e| *e += 1).or_insert(1);
+        *stones.entry(stone?).or_insert(0) += 1;
     }
     Ok(stones)
 }
 
-fn solve_efficient(mut stones: HashStones, blinks: usize) -> usize {
-    for _ in 0..blinks {
-        tick_efficient(&mut stones);
+/// Stats before the first and after every blink. Stops early at the first blink whose stone
+/// count would overflow, so the result can be shorter than `blinks + 1`.
+fn evolve(mut stones: HashStones, blinks: usize) -> Vec<BlinkStats> {
+    let mut stats = vec![BlinkStats::new(&stones, None)];
+    for _ in 1..=blinks {
+        let Some(next) = tick_efficient(&stones) else {
+            break;
+        };
+        stats.push(BlinkStats::new(&next, Some(&stones)));
+        stones = next;
     }
-    stones.values().sum()
+    stats
 }
 
-fn tick_efficient(stones: &mut HashStones) {
+fn tick_efficient(stones: &HashStones) -> Option<HashStones> {
     let mut new_entries = HashStones::with_capacity(stones.len());
     for (value, num) in stones.iter() {
         let mut current_value = *value;
         if let Some(new_value) = update_stone(&mut current_value) {
-            new_entries
-                .entry(new_value)
-                .and_modify(|e| *e += *num)
-                .or_insert(*num);
+            let e = new_entries.entry(new_value).or_insert(0);
+            *e = e.checked_add(*num)?;
         }
-        new_entries
-            .entry(current_value)
-            .and_modify(|e| *e += *num)
-            .or_insert(*num);
+        let e = new_entries.entry(current_value).or_insert(0);
+        *e = e.checked_add(*num)?;
     }
-    *stones = new_entries;
+    // the sum of all counts has to fit as well
+    new_entries
+        .values()
+        .try_fold(0_u128, |acc, n| acc.checked_add(*n))?;
+    Some(new_entries)
 }
 
 fn update_stone(stone: &mut u64) -> Option<u64> {
     if *stone == 0 {
         *stone = 1;
-    } else if (stone.ilog10() + 1) % 2 == 0 {
-        let div = 10_u64.pow((stone.ilog10() + 1) / 2);
+        return None;
+    }
+    let digits = stone.ilog10() + 1;
+    if digits.is_multiple_of(2) {
+        let div = 10_u64.pow(digits / 2);
         let first_half = *stone / div;
         let second_half = *stone % div;
         *stone = first_half;
         return Some(second_half);
-    } else {
-        *stone *= 2024;
     }
+    *stone *= 2024;
     None
 }
 
 #[cfg(test)]
 mod tests {
-    use super::update_stone;
+    use super::*;
 
     #[test]
     fn split_stone() {
@@ -76,4 +122,20 @@ mod tests {
         assert_eq!(stone2, Some(10));
         assert_eq!(stone, 20);
     }
+
+    #[test]
+    fn example_counts() {
+        let stones = HashStones::from([(125, 1), (17, 1)]);
+        let stats = evolve(stones, 25);
+        assert_eq!(stats[6].total, 22);
+        assert_eq!(stats[25].total, 55312);
+    }
+
+    #[test]
+    fn stops_at_overflow() {
+        let stones = HashStones::from([(125, 1), (17, 1)]);
+        let stats = evolve(stones, 1000);
+        assert!(stats.len() > 76 && stats.len() < 1001);
+        assert!(stats.last().unwrap().total > u128::MAX / 10);
+    }
 }
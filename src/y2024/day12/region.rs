@@ -0,0 +1,208 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+/// (row, column)
+pub type Point = (usize, usize);
+
+const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+/// Connected area of equal cells in a grid.
+#[derive(Debug, Clone)]
+pub struct Region<T> {
+    pub label: T,
+    pub cells: Vec<Point>,
+    pub perimeter: usize,
+    pub sides: usize,
+    pub bounds: BoundingBox,
+    /// ids of all regions sharing an edge with this one
+    pub neighbours: Vec<usize>,
+    /// ids of all regions completely enclosed by this one
+    pub holes: Vec<usize>,
+}
+
+impl<T> Region<T> {
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+impl<T: Display> Display for Region<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "type: {}", self.label)?;
+        writeln!(f, "area: {}", self.area())?;
+        writeln!(f, "perimeter: {}", self.perimeter)?;
+        writeln!(f, "sides: {}", self.sides)?;
+        writeln!(f, "bounds: {:?} - {:?}", self.bounds.min, self.bounds.max)?;
+        writeln!(f, "neighbours: {:?}", self.neighbours)?;
+        writeln!(f, "holes: {:?}", self.holes)?;
+        Ok(())
+    }
+}
+
+/// Splits a grid into its regions. Rows may differ in length.
+#[derive(Debug)]
+pub struct RegionMap<T> {
+    pub regions: Vec<Region<T>>,
+    ids: Vec<Vec<usize>>,
+}
+
+impl<T: Copy + Eq> RegionMap<T> {
+    pub fn new(grid: &[Vec<T>]) -> Self {
+        let mut map = Self {
+            regions: Vec::new(),
+            ids: grid.iter().map(|row| vec![usize::MAX; row.len()]).collect(),
+        };
+        for (i, row) in grid.iter().enumerate() {
+            for j in 0..row.len() {
+                if map.ids[i][j] == usize::MAX {
+                    map.flood_fill(grid, (i, j));
+                }
+            }
+        }
+        for id in 0..map.regions.len() {
+            map.measure(id);
+        }
+        for id in 0..map.regions.len() {
+            map.regions[id].holes = map.find_holes(id);
+        }
+        map
+    }
+
+    pub fn region_at(&self, point: Point) -> Option<&Region<T>> {
+        let id = self.ids.get(point.0)?.get(point.1)?;
+        self.regions.get(*id)
+    }
+
+    fn id_at(&self, i: isize, j: isize) -> Option<usize> {
+        if i < 0 || j < 0 {
+            return None;
+        }
+        self.ids.get(i as usize)?.get(j as usize).copied()
+    }
+
+    /// Iterative flood fill, so large regions do not overflow the stack.
+    fn flood_fill(&mut self, grid: &[Vec<T>], start: Point) {
+        let id = self.regions.len();
+        let label = grid[start.0][start.1];
+        let mut cells = Vec::new();
+        let mut stack = vec![start];
+        self.ids[start.0][start.1] = id;
+        while let Some(current) = stack.pop() {
+            cells.push(current);
+            for (di, dj) in DIRECTIONS {
+                let (i, j) = (current.0 as isize + di, current.1 as isize + dj);
+                if self.id_at(i, j) == Some(usize::MAX) && grid[i as usize][j as usize] == label {
+                    self.ids[i as usize][j as usize] = id;
+                    stack.push((i as usize, j as usize));
+                }
+            }
+        }
+        cells.sort();
+        self.regions.push(Region {
+            label,
+            bounds: BoundingBox {
+                min: (
+                    cells.iter().map(|c| c.0).min().unwrap_or(start.0),
+                    cells.iter().map(|c| c.1).min().unwrap_or(start.1),
+                ),
+                max: (
+                    cells.iter().map(|c| c.0).max().unwrap_or(start.0),
+                    cells.iter().map(|c| c.1).max().unwrap_or(start.1),
+                ),
+            },
+            cells,
+            perimeter: 0,
+            sides: 0,
+            neighbours: Vec::new(),
+            holes: Vec::new(),
+        });
+    }
+
+    /// Computes perimeter, neighbours and sides. The number of sides of a polygon equals its
+    /// number of corners, which are counted per cell.
+    fn measure(&mut self, id: usize) {
+        let mut perimeter = 0;
+        let mut corners = 0;
+        let mut neighbours = BTreeSet::new();
+        for (i, j) in &self.regions[id].cells {
+            let (i, j) = (*i as isize, *j as isize);
+            let same = |(di, dj): (isize, isize)| self.id_at(i + di, j + dj) == Some(id);
+            for (di, dj) in DIRECTIONS {
+                match self.id_at(i + di, j + dj) {
+                    Some(other) if other == id => {}
+                    Some(other) => {
+                        perimeter += 1;
+                        neighbours.insert(other);
+                    }
+                    None => perimeter += 1,
+                }
+            }
+            for k in 0..DIRECTIONS.len() {
+                let a = DIRECTIONS[k];
+                let b = DIRECTIONS[(k + 1) % DIRECTIONS.len()];
+                let (has_a, has_b) = (same(a), same(b));
+                let has_diagonal = same((a.0 + b.0, a.1 + b.1));
+                if (!has_a && !has_b) || (has_a && has_b && !has_diagonal) {
+                    corners += 1;
+                }
+            }
+        }
+        let region = &mut self.regions[id];
+        region.perimeter = perimeter;
+        region.sides = corners;
+        region.neighbours = neighbours.into_iter().collect();
+    }
+
+    /// Regions in components of the complement that do not reach beyond the bounding box.
+    fn find_holes(&self, id: usize) -> Vec<usize> {
+        let BoundingBox { min, max } = self.regions[id].bounds;
+        let (height, width) = (max.0 - min.0 + 1, max.1 - min.1 + 1);
+        let mut seen = vec![false; height * width];
+        let mut holes = BTreeSet::new();
+        for i in min.0..=max.0 {
+            for j in min.1..=max.1 {
+                let local = (i - min.0) * width + (j - min.1);
+                if seen[local] || self.id_at(i as isize, j as isize).is_none_or(|o| o == id) {
+                    continue;
+                }
+                let mut enclosed = true;
+                let mut component = BTreeSet::new();
+                let mut stack = vec![(i, j)];
+                seen[local] = true;
+                while let Some(current) = stack.pop() {
+                    component.insert(self.ids[current.0][current.1]);
+                    for (di, dj) in DIRECTIONS {
+                        let (ni, nj) = (current.0 as isize + di, current.1 as isize + dj);
+                        match self.id_at(ni, nj) {
+                            Some(other) if other == id => continue,
+                            Some(_) => {}
+                            None => {
+                                enclosed = false;
+                                continue;
+                            }
+                        }
+                        let (ni, nj) = (ni as usize, nj as usize);
+                        if ni < min.0 || ni > max.0 || nj < min.1 || nj > max.1 {
+                            enclosed = false;
+                            continue;
+                        }
+                        let local = (ni - min.0) * width + (nj - min.1);
+                        if !seen[local] {
+                            seen[local] = true;
+                            stack.push((ni, nj));
+                        }
+                    }
+                }
+                if enclosed {
+                    holes.extend(component);
+                }
+            }
+        }
+        holes.into_iter().collect()
+    }
+}
@@ -1,11 +1,22 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::path::PathBuf;
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
+const PART2_OFFSET: i64 = 10000000000000;
+
+pub const PARAMS: &[&str] = &["table"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    let show_table = params.flag("table")? || verbosity > 1;
     let mut claw_machines = parse(&data, false)?;
     let res = solve(&mut claw_machines);
+    if show_table {
+        print_table(&claw_machines);
+    }
     let mut claw_machines2 = parse(&data, true)?;
     let res2 = solve(&mut claw_machines2);
+    if show_table {
+        print_table(&claw_machines2);
+    }
     println!("res1: {}, res2: {}", res, res2);
     Ok(())
 }
@@ -13,7 +24,11 @@ pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
 #[derive(Default, Debug, PartialEq, PartialOrd, Ord, Eq, Clone)]
 enum SolveCost {
     Unsolvable,
-    Solved(usize),
+    Solved {
+        a: i128,
+        b: i128,
+        cost: i128,
+    },
     #[default]
     Unspecified,
 }
@@ -32,93 +47,201 @@ struct ClawMachine {
     solve_cost: SolveCost,
 }
 
-fn solve(machines: &mut [ClawMachine]) -> usize {
+fn solve(machines: &mut [ClawMachine]) -> i128 {
     for machine in machines.iter_mut() {
-        if let Some(cost) = get_min(machine.da, machine.db, machine.prize) {
-            machine.solve_cost = SolveCost::Solved(cost);
-        } else {
-            machine.solve_cost = SolveCost::Unsolvable;
-        }
+        machine.solve_cost = match get_min(machine.da, machine.db, machine.prize) {
+            Some((a, b)) => SolveCost::Solved {
+                a,
+                b,
+                cost: 3 * a + b,
+            },
+            None => SolveCost::Unsolvable,
+        };
     }
     machines
         .iter()
         .filter_map(|machine| match machine.solve_cost {
             SolveCost::Unsolvable | SolveCost::Unspecified => None,
-            SolveCost::Solved(cost) => Some(cost),
+            SolveCost::Solved { cost, .. } => Some(cost),
         })
         .sum()
 }
 
-fn get_min(b1: Point, b2: Point, target: Point) -> Option<usize> {
-    let det = b1.x * b2.y - b1.y * b2.x;
-    if det == 0 {
+fn print_table(machines: &[ClawMachine]) {
+    println!(
+        "{:>4} | {:>30} | {:>16} | {:>16} | {:>16}",
+        "#", "prize", "A", "B", "cost"
+    );
+    for (i, machine) in machines.iter().enumerate() {
+        let prize = format!("{},{}", machine.prize.x, machine.prize.y);
+        match &machine.solve_cost {
+            SolveCost::Solved { a, b, cost } => {
+                println!("{i:>4} | {prize:>30} | {a:>16} | {b:>16} | {cost:>16}")
+            }
+            other => println!(
+                "{i:>4} | {prize:>30} | {:>16} | {:>16} | {:>16}",
+                "-",
+                "-",
+                format!("{other:?}")
+            ),
+        }
+    }
+    println!();
+}
+
+/// Cheapest non-negative presses (a, b) with a * b1 + b * b2 = target, where a press of A costs 3.
+fn get_min(b1: Point, b2: Point, target: Point) -> Option<(i128, i128)> {
+    let (b1x, b1y, b2x, b2y) = (b1.x as i128, b1.y as i128, b2.x as i128, b2.y as i128);
+    let (tx, ty) = (target.x as i128, target.y as i128);
+    let det = b1x * b2y - b1y * b2x;
+    if det != 0 {
+        // cramer's rule
+        let a = tx * b2y - ty * b2x;
+        let b = b1x * ty - b1y * tx;
+        if a % det != 0 || b % det != 0 {
+            return None;
+        }
+        let (a, b) = (a / det, b / det);
+        return (a >= 0 && b >= 0).then_some((a, b));
+    }
+    // both buttons move along the same line, the target has to be on it as well
+    if b1x * ty - b1y * tx != 0 || b2x * ty - b2y * tx != 0 {
         return None;
     }
-    let xb = (target.y as f64 - b1.y as f64 / b1.x as f64 * target.x as f64)
-        / (b2.y as f64 - b1.y as f64 / b1.x as f64 * b2.x as f64);
-    let xa = (target.x as f64 - xb * b2.x as f64) / b1.x as f64;
-    let xa = xa.round() as i64;
-    let xb = xb.round() as i64;
-    if xa < 0 || xb < 0 || (xa * b1.x + xb * b2.x, xa * b1.y + xb * b2.y) != (target.x, target.y) {
+    if b1x != 0 || b2x != 0 {
+        min_on_line(b1x, b2x, tx)
+    } else if b1y != 0 || b2y != 0 {
+        min_on_line(b1y, b2y, ty)
+    } else {
+        (tx == 0 && ty == 0).then_some((0, 0))
+    }
+}
+
+/// Cheapest non-negative solution of a * u + b * v = t. All solutions lie on
+/// a = a0 + k * v / g, b = b0 - k * u / g and the cost is linear in k,
+/// so the optimum is at one end of the feasible range of k.
+fn min_on_line(u: i128, v: i128, t: i128) -> Option<(i128, i128)> {
+    let (g, x, y) = extended_gcd(u, v);
+    if t % g != 0 {
         return None;
     }
-    Some(3 * xa as usize + xb as usize)
+    let (a0, b0) = (x * (t / g), y * (t / g));
+    let (da, db) = (v / g, -u / g);
+    let (mut lo, mut hi) = (None, None);
+    for (c, m) in [(a0, da), (b0, db)] {
+        // c + k * m >= 0
+        match m.signum() {
+            1 => lo = lo.max(Some(-(c.div_euclid(m)))),
+            -1 => {
+                let bound = c.div_euclid(-m);
+                hi = Some(hi.map_or(bound, |hi: i128| hi.min(bound)));
+            }
+            _ if c < 0 => return None,
+            _ => {}
+        }
+    }
+    if let (Some(lo), Some(hi)) = (lo, hi)
+        && lo > hi
+    {
+        return None;
+    }
+    let slope = 3 * da + db;
+    let k = if slope < 0 { hi.or(lo) } else { lo.or(hi) }.unwrap_or(0);
+    Some((a0 + k * da, b0 + k * db))
+}
+
+/// (g, x, y) with u * x + v * y = g and g > 0
+fn extended_gcd(u: i128, v: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (u, v);
+    let (mut old_s, mut s) = (1, 0);
+    let (mut old_t, mut t) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+        (old_t, t) = (t, old_t - q * t);
+    }
+    if old_r < 0 {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+fn parse_point(line: Option<&&str>, prefix: &str) -> Result<Point> {
+    let err = || AOCError::ParseError(format!("could not parse {prefix}"));
+    let (x, y) = line
+        .and_then(|line| line.strip_prefix(prefix))
+        .and_then(|rest| rest.split_once(", "))
+        .ok_or_else(err)?;
+    let coord = |c: &str, axis: char| {
+        c.strip_prefix(axis)
+            .and_then(|c| c.get(1..))
+            .and_then(|c| c.parse::<i64>().ok())
+            .ok_or_else(err)
+    };
+    Ok(Point {
+        x: coord(x, 'X')?,
+        y: coord(y, 'Y')?,
+    })
 }
 
 fn parse(data: &PathBuf, part2: bool) -> Result<Vec<ClawMachine>> {
     let f = std::fs::read_to_string(data)?;
+    parse_str(&f, part2)
+}
+
+fn parse_str(f: &str, part2: bool) -> Result<Vec<ClawMachine>> {
     let f = f.lines().collect::<Vec<&str>>();
     f.split(|line| line.is_empty())
+        .filter(|lines| !lines.is_empty())
         .map(|lines| {
-            if let (Some(a), Some(b), Some(s)) = (lines.first(), lines.get(1), lines.get(2)) {
-                if let (Some(a), Some(b), Some(s)) = (
-                    a.strip_prefix("Button A: X"),
-                    b.strip_prefix("Button B: X"),
-                    s.strip_prefix("Prize: X="),
-                ) {
-                    let (a, b, s) = (
-                        a.split(',').collect::<Vec<&str>>(),
-                        b.split(',').collect::<Vec<&str>>(),
-                        s.split(',').collect::<Vec<&str>>(),
-                    );
-                    let dxa = a[0]
-                        .parse::<i64>()
-                        .map_err(|_e| AOCError::ParseError("could not parse dxa".into()))?;
-                    let dxb = b[0]
-                        .parse::<i64>()
-                        .map_err(|_e| AOCError::ParseError("could not parse dxb".into()))?;
-                    let xs = s[0]
-                        .parse::<i64>()
-                        .map_err(|_e| AOCError::ParseError("could not parse xs".into()))?;
-                    if let (Some(a), Some(b), Some(s)) = (a.get(1), b.get(1), s.get(1)) {
-                        if let (Some(a), Some(b), Some(s)) = (
-                            a.strip_prefix(" Y"),
-                            b.strip_prefix(" Y"),
-                            s.strip_prefix(" Y="),
-                        ) {
-                            let dya = a
-                                .parse::<i64>()
-                                .map_err(|_e| AOCError::ParseError("could not parse dya".into()))?;
-                            let dyb = b
-                                .parse::<i64>()
-                                .map_err(|_e| AOCError::ParseError("could not parse dyb".into()))?;
-                            let ys = s
-                                .parse::<i64>()
-                                .map_err(|_e| AOCError::ParseError("could not part ys".into()))?;
-                            return Ok(ClawMachine {
-                                prize: Point {
-                                    x: if part2 { xs + 10000000000000 } else { xs },
-                                    y: if part2 { ys + 10000000000000 } else { ys },
-                                },
-                                da: Point { x: dxa, y: dya },
-                                db: Point { x: dxb, y: dyb },
-                                solve_cost: SolveCost::Unspecified,
-                            });
-                        }
-                    }
-                }
-            }
-            Err(AOCError::ParseError("could not parse input".into()))
+            let prize = parse_point(lines.get(2), "Prize: ")?;
+            let offset = if part2 { PART2_OFFSET } else { 0 };
+            Ok(ClawMachine {
+                prize: Point {
+                    x: prize.x + offset,
+                    y: prize.y + offset,
+                },
+                da: parse_point(lines.first(), "Button A: ")?,
+                db: parse_point(lines.get(1), "Button B: ")?,
+                solve_cost: SolveCost::Unspecified,
+            })
         })
         .collect::<Result<Vec<ClawMachine>>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Button A: X+94, Y+34\nButton B: X+22, Y+67\nPrize: X=8400, Y=5400\n\nButton A: X+26, Y+66\nButton B: X+67, Y+21\nPrize: X=12748, Y=12176\n\nButton A: X+17, Y+86\nButton B: X+84, Y+37\nPrize: X=7870, Y=6450\n\nButton A: X+69, Y+23\nButton B: X+27, Y+71\nPrize: X=18641, Y=10279\n";
+
+    #[test]
+    fn example() {
+        let mut machines = parse_str(EXAMPLE, false).unwrap();
+        assert_eq!(solve(&mut machines), 480);
+        assert_eq!(
+            machines[0].solve_cost,
+            SolveCost::Solved {
+                a: 80,
+                b: 40,
+                cost: 280
+            }
+        );
+        let mut machines = parse_str(EXAMPLE, true).unwrap();
+        assert_eq!(solve(&mut machines), 875318608908);
+    }
+
+    #[test]
+    fn collinear_buttons() {
+        let p = |x, y| Point { x, y };
+        // A moves 4 times as far as B, so pressing A is cheaper per distance
+        assert_eq!(get_min(p(4, 8), p(1, 2), p(10, 20)), Some((2, 2)));
+        // B is cheaper per distance
+        assert_eq!(get_min(p(2, 2), p(1, 1), p(5, 5)), Some((0, 5)));
+        assert_eq!(get_min(p(4, 4), p(6, 6), p(7, 7)), None);
+        assert_eq!(get_min(p(4, 4), p(6, 6), p(7, 8)), None);
+        assert_eq!(get_min(p(0, 4), p(0, 6), p(0, 14)), Some((2, 1)));
+    }
+}
@@ -1,206 +1,215 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::{collections::HashMap, fmt::Display, path::PathBuf};
 
-const MAX_X: i32 = 101 - 1;
-const MAX_Y: i32 = 103 - 1;
-const MIN_X: i32 = 0;
-const MIN_Y: i32 = 0;
-const MID_X: i32 = 50;
-const MID_Y: i32 = 51;
-
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
-    let mut area = Area::parse(data)?;
-    let res1 = solve(&mut area, verbosity);
-    println!("res1: {}, res2: {}", res1.0, res1.1);
+const WIDTH: i64 = 101;
+const HEIGHT: i64 = 103;
+const SECONDS: i64 = 100;
+
+pub const PARAMS: &[&str] = &["width", "height", "seconds"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    let room = Room {
+        width: params.get_or("width", WIDTH)?,
+        height: params.get_or("height", HEIGHT)?,
+    };
+    if room.width <= 0 || room.height <= 0 {
+        return Err(AOCError::GenError(
+            "room dimensions must be positive".into(),
+        ));
+    }
+    let area = Area::parse(data, room)?;
+    if verbosity > 2 {
+        println!("{}", area.snapshot(0));
+    }
+    let res1 = area
+        .snapshot(params.get_or("seconds", SECONDS)?)
+        .safety_factor();
+    let res2 = find_tree(&area);
+    if verbosity > 1 {
+        println!("{}", area.snapshot(res2));
+    }
+    println!("res1: {}, res2: {}", res1, res2);
     Ok(())
 }
 
-fn solve(robots: &mut Area, verbosity: u8) -> (usize, usize) {
-    if verbosity > 2 {
-        println!("{robots}");
+/// The robots' x coordinates repeat every `width` seconds and their y coordinates every `height`
+/// seconds. The tree is the time where both are least spread out, so the best x and y phases are
+/// found separately and combined with the chinese remainder theorem.
+fn find_tree(area: &Area) -> i64 {
+    let room = area.room;
+    let tx = (0..room.width)
+        .min_by_key(|t| variance(area.robots.iter().map(|r| r.position_at(*t, &room).x)))
+        .unwrap_or(0);
+    let ty = (0..room.height)
+        .min_by_key(|t| variance(area.robots.iter().map(|r| r.position_at(*t, &room).y)))
+        .unwrap_or(0);
+    if let Some(t) = crt(tx, room.width, ty, room.height) {
+        return t;
     }
-    let mut tree_idx = 0;
-    let mut safety = 0;
-    for i in 0..100000 {
-        if verbosity > 1 && robots.is_c_tree() {
-            println!("{i}");
-            println!("{robots}");
-            if tree_idx == 0 {
-                tree_idx = i;
-            }
-            if i > 99 {
-                break;
-            }
-        }
-        robots.step();
-        if i == 99 {
-            safety = robots.safety_factor();
-        }
-        if verbosity > 2 {
-            println!("{i}");
-            println!("{robots}");
-        }
+    // periods are not coprime, so search the full cycle
+    let cycle = room.width / gcd(room.width, room.height) * room.height;
+    (0..cycle)
+        .min_by_key(|t| {
+            let positions = area
+                .robots
+                .iter()
+                .map(|r| r.position_at(*t, &room))
+                .collect::<Vec<Point>>();
+            variance(positions.iter().map(|p| p.x)) + variance(positions.iter().map(|p| p.y))
+        })
+        .unwrap_or(0)
+}
+
+/// n² times the variance of the values, which keeps the computation in integers.
+fn variance(values: impl Iterator<Item = i64>) -> i64 {
+    let (n, sum, sum_sq) = values.fold((0, 0, 0), |(n, sum, sum_sq), v| {
+        (n + 1, sum + v, sum_sq + v * v)
+    });
+    n * sum_sq - sum * sum
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+/// Smallest t >= 0 with t = a mod m and t = b mod n, if m and n are coprime.
+fn crt(a: i64, m: i64, b: i64, n: i64) -> Option<i64> {
+    if gcd(m, n) != 1 {
+        return None;
     }
-    (safety, tree_idx)
+    // t = a + m * k with m * k = b - a mod n
+    let inv = (1..n.max(2)).find(|k| (m * k).rem_euclid(n) == 1 % n)?;
+    let k = ((b - a) * inv).rem_euclid(n);
+    Some((a + m * k).rem_euclid(m * n))
 }
 
 #[derive(Default, Debug, PartialEq, PartialOrd, Ord, Eq, Clone, Hash, Copy)]
 struct Point {
-    x: i32,
-    y: i32,
-}
-
-impl std::ops::Add for Point {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self::Output {
-        let mut next_x = self.x + rhs.x;
-        let mut next_y = self.y + rhs.y;
-        if next_x > MAX_X {
-            next_x -= MAX_X + 1;
-        } else if next_x < MIN_X {
-            next_x += MAX_X + 1;
-        }
-        if next_y > MAX_Y {
-            next_y -= MAX_Y + 1;
-        } else if next_y < MIN_Y {
-            next_y += MAX_Y + 1;
-        }
+    x: i64,
+    y: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Room {
+    width: i64,
+    height: i64,
+}
+
+impl Room {
+    fn mid(&self) -> Point {
         Point {
-            x: next_x,
-            y: next_y,
+            x: self.width / 2,
+            y: self.height / 2,
         }
     }
 }
 
 #[derive(Default, Debug, PartialEq, PartialOrd, Ord, Eq, Clone)]
 struct Robot {
+    start: Point,
     velocity: Point,
 }
 
-#[derive(Default, Debug)]
+impl Robot {
+    fn position_at(&self, t: i64, room: &Room) -> Point {
+        Point {
+            x: (self.start.x + self.velocity.x * t).rem_euclid(room.width),
+            y: (self.start.y + self.velocity.y * t).rem_euclid(room.height),
+        }
+    }
+}
+
+#[derive(Debug)]
 struct Area {
-    robots: HashMap<Point, Vec<Robot>>,
+    robots: Vec<Robot>,
+    room: Room,
 }
 
 impl Area {
-    fn parse(data: PathBuf) -> Result<Self> {
+    fn parse(data: PathBuf, room: Room) -> Result<Self> {
         let f = std::fs::read_to_string(data)?;
-        Ok(Self {
-            robots: f.lines().try_fold(HashMap::new(), |mut acc, line| {
+        Self::parse_str(&f, room)
+    }
+
+    fn parse_str(f: &str, room: Room) -> Result<Self> {
+        let parse_pair = |s: Option<&str>, prefix: &str| -> Result<Point> {
+            let (x, y) = s
+                .and_then(|s| s.strip_prefix(prefix))
+                .and_then(|s| s.split_once(','))
+                .ok_or(AOCError::ParseError(format!("could not find {prefix}")))?;
+            Ok(Point {
+                x: x.parse::<i64>()
+                    .map_err(|_e| AOCError::ParseError(format!("could not parse {x}")))?,
+                y: y.parse::<i64>()
+                    .map_err(|_e| AOCError::ParseError(format!("could not parse {y}")))?,
+            })
+        };
+        let robots = f
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
                 let mut l = line.split_whitespace();
-                if let (Some(p), Some(v)) = (l.next(), l.next()) {
-                    let mut p = p.strip_prefix("p=").expect("parsing failed").split(',');
-                    let mut v = v.strip_prefix("v=").expect("parsing failed").split(',');
-                    if let (Some(px), Some(py), Some(vx), Some(vy)) =
-                        (p.next(), p.next(), v.next(), v.next())
-                    {
-                        let point = Point {
-                            x: px
-                                .parse::<i32>()
-                                .map_err(|_e| AOCError::ParseError("could not parse px".into()))?,
-                            y: py
-                                .parse::<i32>()
-                                .map_err(|_e| AOCError::ParseError("could not parse py".into()))?,
-                        };
-
-                        let robot = Robot {
-                            //current: point.clone(),
-                            velocity: Point {
-                                x: vx.parse::<i32>().map_err(|_e| {
-                                    AOCError::ParseError("could not parse px".into())
-                                })?,
-                                y: vy.parse::<i32>().map_err(|_e| {
-                                    AOCError::ParseError("could not parse py".into())
-                                })?,
-                            },
-                        };
-
-                        acc.entry(point)
-                            .and_modify(|e: &mut Vec<Robot>| e.push(robot.clone()))
-                            .or_insert(vec![robot]);
-                    }
-                    Ok(acc)
-                } else {
-                    Err(AOCError::ParseError("could not parse line".into()))
-                }
-            })?,
-        })
+                Ok(Robot {
+                    start: parse_pair(l.next(), "p=")?,
+                    velocity: parse_pair(l.next(), "v=")?,
+                })
+            })
+            .collect::<Result<Vec<Robot>>>()?;
+        Ok(Self { robots, room })
     }
 
-    fn step(&mut self) {
-        let mut next_area = HashMap::new();
-        for (point, robots) in self.robots.iter() {
-            for robot in robots.iter() {
-                next_area
-                    .entry(*point + robot.velocity)
-                    .and_modify(|e: &mut Vec<Robot>| e.push(robot.clone()))
-                    .or_insert(vec![robot.clone()]);
-            }
+    fn snapshot(&self, t: i64) -> Snapshot {
+        let mut robots = HashMap::new();
+        for robot in &self.robots {
+            *robots.entry(robot.position_at(t, &self.room)).or_insert(0) += 1;
+        }
+        Snapshot {
+            robots,
+            room: self.room,
+            time: t,
         }
-        self.robots = next_area;
     }
+}
+
+/// Robot counts per tile at a given time.
+struct Snapshot {
+    robots: HashMap<Point, usize>,
+    room: Room,
+    time: i64,
+}
 
+impl Snapshot {
     fn safety_factor(&self) -> usize {
-        let midpoint_x = MID_X;
-        let midpoint_y = MID_Y;
+        let mid = self.room.mid();
         let (mut q1, mut q2, mut q3, mut q4) = (0, 0, 0, 0);
         for (point, robots) in self.robots.iter() {
-            if point.x == midpoint_x || point.y == midpoint_y {
+            if point.x == mid.x || point.y == mid.y {
                 continue;
             }
-            if point.x < midpoint_x && point.y < midpoint_y {
-                q1 += robots.len();
-            } else if point.x > midpoint_x && point.y < midpoint_y {
-                q2 += robots.len();
-            } else if point.x < midpoint_x && point.y > midpoint_y {
-                q3 += robots.len();
-            } else if point.x > midpoint_x && point.y > midpoint_y {
-                q4 += robots.len();
+            if point.x < mid.x && point.y < mid.y {
+                q1 += robots;
+            } else if point.x > mid.x && point.y < mid.y {
+                q2 += robots;
+            } else if point.x < mid.x && point.y > mid.y {
+                q3 += robots;
+            } else if point.x > mid.x && point.y > mid.y {
+                q4 += robots;
             }
         }
         q1 * q2 * q3 * q4
     }
-
-    fn is_c_tree(&self) -> bool {
-        let w_size_y = (MAX_Y - MIN_Y) / 10;
-        let w_size_x = (MAX_X - MIN_X) / 10;
-        let t = ((w_size_x * w_size_y) as f64 * 0.8) as usize;
-        for y in MIN_Y..=MAX_Y / 10 {
-            for x in MIN_X..=MAX_X / 10 {
-                //calculate density around the point in a nxm window. if it exceeds some threshold, a tree is likely found
-                let p0 = Point {
-                    x: x * 10,
-                    y: y * 10,
-                };
-                let mut n_robots = 0;
-                for dx in 0..w_size_x {
-                    for dy in 0..w_size_y {
-                        if let Some(robots) = self.robots.get(&Point {
-                            x: p0.x + dx,
-                            y: p0.y + dy,
-                        }) {
-                            n_robots += robots.len();
-                        }
-                    }
-                }
-                if n_robots >= t {
-                    return true;
-                }
-            }
-        }
-        false
-    }
 }
 
-impl Display for Area {
+impl Display for Snapshot {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "area:")?;
-        for i in MIN_Y..=MAX_Y {
+        let mid = self.room.mid();
+        writeln!(f, "area after {}s:", self.time)?;
+        for i in 0..self.room.height {
             writeln!(f)?;
-            for j in MIN_X..=MAX_X {
+            for j in 0..self.room.width {
                 if let Some(robots) = self.robots.get(&Point { x: j, y: i }) {
-                    write!(f, "{}", robots.len())?;
-                } else if i == MID_Y || j == MID_X {
+                    write!(f, "{}", robots)?;
+                } else if i == mid.y || j == mid.x {
                     write!(f, " ")?;
                 } else {
                     write!(f, ".")?;
@@ -211,3 +220,26 @@ impl Display for Area {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_safety_factor() {
+        let f = "p=0,4 v=3,-3\np=6,3 v=-1,-3\np=10,3 v=-1,2\np=2,0 v=2,-1\np=0,0 v=1,3\np=3,0 v=-2,-2\np=7,6 v=-1,-3\np=3,0 v=-1,-2\np=9,3 v=2,3\np=7,3 v=-1,2\np=2,4 v=2,-3\np=9,5 v=-3,-3\n";
+        let room = Room {
+            width: 11,
+            height: 7,
+        };
+        let area = Area::parse_str(f, room).unwrap();
+        assert_eq!(area.snapshot(100).safety_factor(), 12);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt(2, 3, 3, 5), Some(8));
+        assert_eq!(crt(0, 101, 0, 103), Some(0));
+        assert_eq!(crt(1, 4, 1, 6), None);
+    }
+}
@@ -1,13 +1,20 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::{
-    collections::HashSet,
+    collections::{HashSet, VecDeque},
     fmt::Display,
     fs::read_to_string,
     ops::{Add, AddAssign, Sub, SubAssign},
     path::PathBuf,
 };
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
+mod replay;
+
+pub const PARAMS: &[&str] = &["interactive", "replay", "wide", "script"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    if params.flag("interactive")? || params.get::<usize>("replay")?.is_some() {
+        return replay::run(&data, params);
+    }
     let mut warehouse = WareHouse::parse(&data)?;
     if verbosity > 2 {
         println!("{}", warehouse);
@@ -67,103 +74,216 @@ impl Sub for Point {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Robot {
     moves: Vec<Point>,
     position: Point,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    Empty,
+    Wall,
+    Box(usize),
+}
+
+/// A box covering any set of cells, which all move together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WareBox {
+    cells: Vec<Point>,
+    /// character to draw custom shapes with, `None` for the standard `O` and `[]` boxes
+    label: Option<char>,
+}
+
+impl WareBox {
+    /// top left corner of the bounding box
+    fn corner(&self) -> Point {
+        Point {
+            x: self.cells.iter().map(|c| c.x).min().unwrap_or_default(),
+            y: self.cells.iter().map(|c| c.y).min().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 struct WareHouse {
     robot: Robot,
-    boxes: HashSet<Point>,
-    walls: HashSet<Point>,
+    boxes: Vec<WareBox>,
+    tiles: Vec<Vec<Tile>>,
     x_bounds: i32,
     y_bounds: i32,
-    fat: bool,
 }
 
 impl WareHouse {
     fn parse(data: &PathBuf) -> Result<Self> {
         let f = read_to_string(data)?;
+        Self::parse_str(&f)
+    }
+
+    /// Besides `O` and `[]` boxes, every 4-connected group of equal letters forms one box,
+    /// which allows boxes of any shape.
+    fn parse_str(f: &str) -> Result<Self> {
         let l = f.lines().collect::<Vec<&str>>();
         let mut l = l.split(|line| line.is_empty());
-        if let (Some(warehousemap), Some(moves)) = (l.next(), l.next()) {
-            let mut walls: HashSet<Point> = HashSet::new();
-            let mut boxes: HashSet<Point> = HashSet::new();
-            let (mut x, mut y) = (0, 0);
-            let mut robot_pos = Point::default();
-            for row in warehousemap.iter() {
-                x = 0;
-                for tile in row.chars() {
-                    match tile {
-                        '#' => {
-                            walls.insert(Point { x, y });
-                        }
-                        '@' => {
-                            robot_pos.x = x;
-                            robot_pos.y = y;
+        let (Some(warehousemap), Some(moves)) = (l.next(), l.next()) else {
+            return Err(AOCError::ParseError("could not parse input".into()));
+        };
+        let chars = warehousemap
+            .iter()
+            .map(|row| row.chars().collect::<Vec<char>>())
+            .collect::<Vec<Vec<char>>>();
+        let x_bounds = chars.iter().map(|row| row.len()).max().unwrap_or(0) as i32;
+        let y_bounds = chars.len() as i32;
+        let at = |p: Point| -> char {
+            if p.x < 0 || p.y < 0 {
+                return '#';
+            }
+            chars
+                .get(p.y as usize)
+                .and_then(|row| row.get(p.x as usize))
+                .copied()
+                .unwrap_or('.')
+        };
+        let mut boxes = Vec::new();
+        let mut taken: HashSet<Point> = HashSet::new();
+        let mut robot_pos = None;
+        for y in 0..y_bounds {
+            for x in 0..x_bounds {
+                let p = Point { x, y };
+                match at(p) {
+                    '@' => robot_pos = Some(p),
+                    'O' => boxes.push(WareBox {
+                        cells: vec![p],
+                        label: None,
+                    }),
+                    '[' if at(p + Point { x: 1, y: 0 }) == ']' => boxes.push(WareBox {
+                        cells: vec![p, p + Point { x: 1, y: 0 }],
+                        label: None,
+                    }),
+                    '[' | ']' => {
+                        if at(p - Point { x: 1, y: 0 }) != '[' {
+                            return Err(AOCError::ParseError(format!(
+                                "unmatched box half at {},{}",
+                                x, y
+                            )));
                         }
-                        'O' => {
-                            boxes.insert(Point { x, y });
+                    }
+                    c if c.is_ascii_alphabetic() && !taken.contains(&p) => {
+                        let mut cells = vec![p];
+                        let mut queue = VecDeque::from([p]);
+                        taken.insert(p);
+                        while let Some(current) = queue.pop_front() {
+                            for delta in DIRECTIONS {
+                                let next = current + delta;
+                                if at(next) == c && taken.insert(next) {
+                                    cells.push(next);
+                                    queue.push_back(next);
+                                }
+                            }
                         }
-                        _ => {}
+                        boxes.push(WareBox {
+                            cells,
+                            label: Some(c),
+                        });
+                    }
+                    '#' | '.' => {}
+                    c if c.is_ascii_alphabetic() => {}
+                    c => {
+                        return Err(AOCError::ParseError(format!("unknown tile {c}")));
                     }
-                    x += 1;
                 }
-                y += 1;
             }
-
-            let robot_moves = moves
-                .concat()
-                .chars()
-                .filter_map(|m| match m {
-                    '>' => Some(Point { x: 1, y: 0 }),
-                    '<' => Some(Point { x: -1, y: 0 }),
-                    '^' => Some(Point { x: 0, y: -1 }),
-                    'v' => Some(Point { x: 0, y: 1 }),
-                    _ => None,
-                })
-                .collect::<Vec<Point>>();
-
-            return Ok(WareHouse {
-                robot: Robot {
-                    moves: robot_moves,
-                    position: robot_pos,
-                },
-                boxes,
-                walls,
-                x_bounds: x,
-                y_bounds: y,
-                fat: false,
-            });
         }
-        Err(AOCError::ParseError("could not parse input".into()))
+        let robot_pos = robot_pos.ok_or(AOCError::ParseError("no robot found".into()))?;
+        let mut tiles = vec![vec![Tile::Empty; x_bounds as usize]; y_bounds as usize];
+        for (y, row) in tiles.iter_mut().enumerate() {
+            for (x, tile) in row.iter_mut().enumerate() {
+                if at(Point {
+                    x: x as i32,
+                    y: y as i32,
+                }) == '#'
+                {
+                    *tile = Tile::Wall;
+                }
+            }
+        }
+        let mut warehouse = WareHouse {
+            robot: Robot {
+                moves: parse_moves(&moves.concat()),
+                position: robot_pos,
+            },
+            boxes: Vec::new(),
+            tiles,
+            x_bounds,
+            y_bounds,
+        };
+        warehouse.place_boxes(boxes);
+        Ok(warehouse)
     }
 
-    fn gen_part2(mut self) -> Self {
-        if self.fat {
-            return self;
+    /// Replaces all boxes, keeping walls and the robot.
+    fn place_boxes(&mut self, boxes: Vec<WareBox>) {
+        for tile in self.tiles.iter_mut().flatten() {
+            if let Tile::Box(_) = tile {
+                *tile = Tile::Empty;
+            }
         }
-        self.fat = true;
-        // fat boxes are anchored at a point x, y which corresponds to their left corner. only that point is stored
-        let mut fat_boxes = HashSet::new();
-        let mut fat_borders = HashSet::new();
-        for x in 0..self.x_bounds {
-            for y in 0..self.x_bounds {
-                let p = Point { x, y };
-                if self.boxes.contains(&p) {
-                    fat_boxes.insert(Point { x: x * 2, y });
-                } else if self.walls.contains(&p) {
-                    fat_borders.insert(Point { x: x * 2, y });
-                    fat_borders.insert(Point { x: x * 2 + 1, y });
-                }
+        for (id, b) in boxes.iter().enumerate() {
+            for c in &b.cells {
+                self.tiles[c.y as usize][c.x as usize] = Tile::Box(id);
             }
         }
-        self.boxes = fat_boxes;
-        self.walls = fat_borders;
+        self.boxes = boxes;
+    }
+
+    fn tile(&self, p: Point) -> Tile {
+        if p.x < 0 || p.y < 0 {
+            return Tile::Wall;
+        }
+        self.tiles
+            .get(p.y as usize)
+            .and_then(|row| row.get(p.x as usize))
+            .copied()
+            .unwrap_or(Tile::Wall)
+    }
+
+    /// Doubles the width of everything, so `O` boxes become `[]`.
+    fn gen_part2(mut self) -> Self {
+        let boxes = self
+            .boxes
+            .iter()
+            .map(|b| WareBox {
+                cells: b
+                    .cells
+                    .iter()
+                    .flat_map(|c| {
+                        [
+                            Point { x: c.x * 2, y: c.y },
+                            Point {
+                                x: c.x * 2 + 1,
+                                y: c.y,
+                            },
+                        ]
+                    })
+                    .collect(),
+                label: b.label,
+            })
+            .collect();
+        self.tiles = self
+            .tiles
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .flat_map(|tile| match tile {
+                        Tile::Wall => [Tile::Wall; 2],
+                        _ => [Tile::Empty; 2],
+                    })
+                    .collect()
+            })
+            .collect();
         self.x_bounds *= 2;
         self.robot.position.x *= 2;
+        self.place_boxes(boxes);
         self
     }
 
@@ -172,175 +292,160 @@ impl WareHouse {
             if verbosity > 3 {
                 println!("move: dx {}, dy {}", next_move.x, next_move.y);
             }
-            if self.fat && next_move.x == 0 {
-                if self.can_mv_box(next_move, self.robot.position + *next_move) {
-                    let mut new_boxes = HashSet::new();
-                    self.recursive_mv_box(
-                        next_move,
-                        self.robot.position + *next_move,
-                        &mut new_boxes,
-                    );
-                    self.robot.position += *next_move;
-                    self.boxes.extend(new_boxes);
-                }
-            } else {
-                self.tick(next_move);
-            }
+            self.step(next_move);
             if verbosity > 3 {
                 println!("{}", self);
             }
         }
     }
 
-    fn tick(&mut self, current_move: &Point) {
-        let mut next_point = self.robot.position;
-        loop {
-            next_point += *current_move;
-            if self.walls.contains(&next_point) {
-                break;
+    /// Collects every box the move would push with a bfs and moves them all at once.
+    /// Nothing moves if any of them is blocked by a wall.
+    ///
+    /// Returns the ids of the pushed boxes, or `None` if the robot could not move.
+    fn step(&mut self, next_move: &Point) -> Option<Vec<usize>> {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        match self.tile(self.robot.position + *next_move) {
+            Tile::Wall => return None,
+            Tile::Box(id) => {
+                seen.insert(id);
+                queue.push_back(id);
             }
-            if !(self.boxes.contains(&next_point)
-                || (self.fat
-                    && self.boxes.contains(&Point {
-                        x: next_point.x - 1,
-                        y: next_point.y,
-                    })))
-            {
-                if self.fat {
-                    loop {
-                        if next_point - *current_move == self.robot.position {
-                            break;
-                        }
-                        if current_move.x == 1 {
-                            next_point -= *current_move;
-                        }
-                        self.boxes.insert(next_point);
-                        next_point -= *current_move;
-                        self.boxes.remove(&next_point);
-                        if current_move.x == -1 {
-                            next_point -= *current_move;
-                        }
-                    }
-                    self.boxes.remove(&next_point);
-                } else {
-                    self.boxes.insert(next_point);
-                    self.boxes.remove(&(self.robot.position + *current_move));
+            Tile::Empty => {}
+        }
+        let mut moving = Vec::new();
+        while let Some(id) = queue.pop_front() {
+            moving.push(id);
+            for c in &self.boxes[id].cells {
+                match self.tile(*c + *next_move) {
+                    Tile::Wall => return None,
+                    Tile::Box(other) if seen.insert(other) => queue.push_back(other),
+                    _ => {}
                 }
-                self.robot.position += *current_move;
-                break;
             }
         }
+        self.shift(&moving, *next_move);
+        self.robot.position += *next_move;
+        Some(moving)
     }
 
-    fn can_mv_box(&self, current_move: &Point, current_point: Point) -> bool {
-        let next_point = current_point + *current_move;
-        if self.walls.contains(&current_point) {
-            return false;
-        }
-        if !self.boxes.contains(&current_point)
-            && !self.boxes.contains(&Point {
-                x: current_point.x - 1,
-                y: current_point.y,
-            })
-        {
-            return true;
+    /// Moves the given boxes by `delta`, without checking what is in the way.
+    fn shift(&mut self, ids: &[usize], delta: Point) {
+        for id in ids {
+            for c in &self.boxes[*id].cells {
+                self.tiles[c.y as usize][c.x as usize] = Tile::Empty;
+            }
         }
-
-        self.can_mv_box(
-            current_move,
-            Point {
-                x: if self.boxes.contains(&current_point) {
-                    next_point.x + 1
-                } else {
-                    next_point.x - 1
-                },
-                y: next_point.y,
-            },
-        ) && self.can_mv_box(
-            current_move,
-            Point {
-                x: next_point.x,
-                y: next_point.y,
-            },
-        )
-    }
-
-    fn recursive_mv_box(
-        &mut self,
-        current_move: &Point,
-        current_point: Point,
-        new_boxes: &mut HashSet<Point>,
-    ) {
-        let next_point = current_point + *current_move;
-        let mut on_spot = false;
-        if self.boxes.contains(&current_point) {
-            if self.boxes.remove(&current_point) {
-                on_spot = true;
+        for id in ids {
+            for c in self.boxes[*id].cells.iter_mut() {
+                *c += delta;
+                self.tiles[c.y as usize][c.x as usize] = Tile::Box(*id);
             }
-            new_boxes.insert(next_point);
-        } else if self.boxes.contains(&Point {
-            x: current_point.x - 1,
-            y: current_point.y,
-        }) {
-            self.boxes.remove(&Point {
-                x: current_point.x - 1,
-
-                y: current_point.y,
-            });
-            new_boxes.insert(Point {
-                x: next_point.x - 1,
-                y: next_point.y,
-            });
-        } else {
-            return;
         }
-        self.recursive_mv_box(current_move, next_point, new_boxes);
-        self.recursive_mv_box(
-            current_move,
-            Point {
-                x: if on_spot {
-                    next_point.x + 1
-                } else {
-                    next_point.x - 1
-                },
-                y: next_point.y,
-            },
-            new_boxes,
-        );
     }
 
     fn coords(&self) -> i32 {
-        let mut tot = 0;
-        for p in self.boxes.iter() {
-            tot += p.x + p.y * 100;
-        }
-        tot
+        self.boxes
+            .iter()
+            .map(|b| {
+                let p = b.corner();
+                p.x + p.y * 100
+            })
+            .sum()
     }
 }
 
+const DIRECTIONS: [Point; 4] = [
+    Point { x: 1, y: 0 },
+    Point { x: -1, y: 0 },
+    Point { x: 0, y: 1 },
+    Point { x: 0, y: -1 },
+];
+
+fn parse_moves(moves: &str) -> Vec<Point> {
+    moves
+        .chars()
+        .filter_map(|m| match m {
+            '>' => Some(Point { x: 1, y: 0 }),
+            '<' => Some(Point { x: -1, y: 0 }),
+            '^' => Some(Point { x: 0, y: -1 }),
+            'v' => Some(Point { x: 0, y: 1 }),
+            _ => None,
+        })
+        .collect::<Vec<Point>>()
+}
+
 impl Display for WareHouse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for y in 0..self.y_bounds {
             writeln!(f)?;
             for x in 0..self.x_bounds {
                 let p = Point { x, y };
-                if self.walls.contains(&p) {
-                    write!(f, "#")?;
-                } else if self.boxes.contains(&p) {
-                    if self.fat {
-                        write!(f, "[")?;
-                    } else {
-                        write!(f, "O")?;
-                    }
-                } else if p == self.robot.position {
+                if p == self.robot.position {
                     write!(f, "@")?;
-                } else if self.fat && self.boxes.contains(&Point { x: x - 1, y }) {
-                    write!(f, "]")?;
-                } else {
-                    write!(f, ".")?;
+                    continue;
+                }
+                match self.tile(p) {
+                    Tile::Wall => write!(f, "#")?,
+                    Tile::Empty => write!(f, ".")?,
+                    Tile::Box(id) => {
+                        let b = &self.boxes[id];
+                        let c = match (b.label, b.cells.len()) {
+                            (Some(label), _) => label,
+                            (None, 2) if b.corner() == p => '[',
+                            (None, 2) => ']',
+                            (None, _) => 'O',
+                        };
+                        write!(f, "{c}")?;
+                    }
                 }
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(map: &str, moves: &str, wide: bool) -> WareHouse {
+        let mut warehouse = WareHouse::parse_str(&format!("{map}\n\n{moves}\n")).unwrap();
+        if wide {
+            warehouse = warehouse.gen_part2();
+        }
+        warehouse.solve(0);
+        warehouse
+    }
+
+    #[test]
+    fn stacked_wide_boxes() {
+        let map = "#######\n#...#.#\n#.....#\n#..OO@#\n#..O..#\n#.....#\n#######";
+        let warehouse = run(map, "<vv<<^^<<^^", true);
+        assert_eq!(warehouse.coords(), 618);
+        let expected = "\n##############\n##...[].##..##\n##...@.[]...##\n##....[]....##\n##..........##\n##..........##\n##############";
+        assert_eq!(warehouse.to_string(), expected);
+    }
+
+    #[test]
+    fn custom_shapes() {
+        // an L shaped box pushed up drags the single box above its foot along
+        let map = "#######\n#.....#\n#..O..#\n#..LL.#\n#...L.#\n#...@.#\n#######";
+        let warehouse = run(map, "^", false);
+        assert_eq!(
+            warehouse.to_string(),
+            "\n#######\n#..O..#\n#..LL.#\n#...L.#\n#...@.#\n#.....#\n#######"
+        );
+        // blocked by the wall now
+        let warehouse = run(map, "^^", false);
+        assert_eq!(warehouse.robot.position, Point { x: 4, y: 4 });
+        // three wide boxes
+        let map = "########\n#......#\n#.@AAA.#\n########";
+        let warehouse = run(map, ">>", false);
+        assert_eq!(
+            warehouse.to_string(),
+            "\n########\n#......#\n#..@AAA#\n########"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use super::{Point, WareHouse, parse_moves};
+use crate::{AOCError, Params, Result};
+use std::{
+    fs::read_to_string,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+const HELP: &str = "input is read a line at a time, so finish every command with Enter
+moves: arrow keys, ^v<> or wasd (several per line are fine)
+u: undo, g N: jump to move N of the script, n: next script move, r: rest of the script, q: quit";
+
+/// What a single move changed, enough to take it back.
+struct Delta {
+    direction: Point,
+    /// boxes pushed along, `None` when the robot was blocked
+    moved: Option<Vec<usize>>,
+    from_script: bool,
+}
+
+/// Steps through a warehouse one move at a time, recording each move for undo.
+struct Session {
+    warehouse: WareHouse,
+    history: Vec<Delta>,
+    /// moves to replay, taken from the input or a script
+    script: Vec<Point>,
+    /// index of the next script move
+    cursor: usize,
+}
+
+impl Session {
+    fn new(warehouse: WareHouse, script: Vec<Point>) -> Self {
+        Self {
+            warehouse,
+            history: Vec::new(),
+            script,
+            cursor: 0,
+        }
+    }
+
+    fn apply(&mut self, next_move: &Point) {
+        let moved = self.warehouse.step(next_move);
+        self.history.push(Delta {
+            direction: *next_move,
+            moved,
+            from_script: false,
+        });
+    }
+
+    /// Applies the next script move, returns false at the end of the script.
+    fn next(&mut self) -> bool {
+        let Some(next_move) = self.script.get(self.cursor).copied() else {
+            return false;
+        };
+        self.apply(&next_move);
+        if let Some(delta) = self.history.last_mut() {
+            delta.from_script = true;
+        }
+        self.cursor += 1;
+        true
+    }
+
+    fn undo(&mut self) -> bool {
+        let Some(delta) = self.history.pop() else {
+            return false;
+        };
+        if let Some(moved) = delta.moved {
+            self.warehouse
+                .shift(&moved, Point::default() - delta.direction);
+            self.warehouse.robot.position -= delta.direction;
+        }
+        if delta.from_script {
+            self.cursor -= 1;
+        }
+        true
+    }
+
+    /// Goes to the state after the first `n` script moves, undoing only as far as needed.
+    fn goto(&mut self, n: usize) {
+        // manual moves are interleaved whenever the history is longer than the script cursor
+        while self.history.len() != self.cursor || self.cursor > n {
+            self.undo();
+        }
+        while self.cursor < n && self.next() {}
+    }
+
+    fn print(&self) {
+        println!("{}", self.warehouse);
+        println!(
+            "moves: {}, script: {}/{}, gps: {}",
+            self.history.len(),
+            self.cursor,
+            self.script.len(),
+            self.warehouse.coords()
+        );
+    }
+}
+
+/// Translates terminal escape sequences for the arrow keys into move characters.
+fn arrows_to_moves(line: &str) -> String {
+    line.replace("\x1b[A", "^")
+        .replace("\x1b[B", "v")
+        .replace("\x1b[C", ">")
+        .replace("\x1b[D", "<")
+        .replace('w', "^")
+        .replace('s', "v")
+        .replace('d', ">")
+        .replace('a', "<")
+}
+
+pub fn run(data: &PathBuf, params: &Params) -> Result<()> {
+    let mut warehouse = WareHouse::parse(data)?;
+    if params.flag("wide")? {
+        warehouse = warehouse.gen_part2();
+    }
+    let script = match params.get::<PathBuf>("script")? {
+        Some(path) => parse_moves(&read_to_string(path)?),
+        None => std::mem::take(&mut warehouse.robot.moves),
+    };
+    let mut session = Session::new(warehouse, script);
+    if let Some(n) = params.get::<usize>("replay")? {
+        if n > session.script.len() {
+            return Err(AOCError::GenError(format!(
+                "script only has {} moves",
+                session.script.len()
+            )));
+        }
+        session.goto(n);
+        session.print();
+        if !params.flag("interactive")? {
+            return Ok(());
+        }
+    } else {
+        session.print();
+    }
+    println!("{HELP}");
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim();
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("q", _) => break,
+            ("u", _) => {
+                if !session.undo() {
+                    println!("nothing to undo");
+                }
+            }
+            ("g", n) => match n.trim().parse::<usize>() {
+                Ok(n) if n <= session.script.len() => session.goto(n),
+                _ => println!("expected a move between 0 and {}", session.script.len()),
+            },
+            ("n", _) => {
+                if !session.next() {
+                    println!("end of script");
+                }
+            }
+            ("r", _) => session.goto(session.script.len()),
+            ("h" | "?", _) => {
+                println!("{HELP}");
+                continue;
+            }
+            _ => {
+                let moves = parse_moves(&arrows_to_moves(line));
+                if moves.is_empty() {
+                    println!("{HELP}");
+                    continue;
+                }
+                for next_move in moves.iter() {
+                    session.apply(next_move);
+                }
+            }
+        }
+        session.print();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+<^^>>>vv<v>>v<<
+";
+
+    fn session() -> Session {
+        let mut warehouse = WareHouse::parse_str(EXAMPLE).unwrap();
+        let script = std::mem::take(&mut warehouse.robot.moves);
+        Session::new(warehouse, script)
+    }
+
+    /// The warehouse after stepping through the first `n` script moves from scratch.
+    fn after(n: usize) -> WareHouse {
+        let mut warehouse = WareHouse::parse_str(EXAMPLE).unwrap();
+        for next_move in warehouse.robot.moves.clone().iter().take(n) {
+            warehouse.step(next_move);
+        }
+        warehouse
+    }
+
+    fn assert_same(a: &WareHouse, b: &WareHouse) {
+        assert_eq!(a.boxes, b.boxes);
+        assert_eq!(a.tiles, b.tiles);
+        assert_eq!(a.robot.position, b.robot.position);
+    }
+
+    #[test]
+    fn goto_and_undo() {
+        let mut session = session();
+        session.goto(15);
+        assert_eq!(session.warehouse.coords(), 2028);
+        session.goto(7);
+        assert_same(&session.warehouse, &after(7));
+        assert_eq!(session.history.len(), 7);
+        session.goto(10);
+        assert_same(&session.warehouse, &after(10));
+        while session.undo() {}
+        assert_same(&session.warehouse, &after(0));
+        assert_eq!(session.cursor, 0);
+    }
+
+    #[test]
+    fn manual_moves_keep_script_cursor() {
+        let mut session = session();
+        session.next();
+        session.apply(&Point { x: 0, y: 1 });
+        session.apply(&Point { x: 0, y: 1 });
+        assert_eq!(session.cursor, 1);
+        session.next();
+        assert_eq!(session.cursor, 2);
+        assert_eq!(session.history.len(), 4);
+        session.goto(3);
+        assert_eq!(session.history.len(), 3);
+        assert_same(&session.warehouse, &after(3));
+    }
+}
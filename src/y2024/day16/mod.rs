@@ -1,182 +1,99 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap, HashSet},
+    collections::{BinaryHeap, HashSet},
     fmt::Display,
     fs,
     ops::Add,
     path::PathBuf,
 };
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["step", "turn", "paths"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
     let maze = Maze::parse(data)?;
+    let costs = Costs {
+        step: params.get_or("step", 1)?,
+        turn: params.get_or("turn", 1000)?,
+    };
+    let show_paths = params.flag("paths")?;
     if verbosity > 2 {
         println!("{}", maze);
     }
-    let res1 = astar(&maze);
-    let res2 = astar_2(&maze);
-    println!("res1: {}, res2: {}", res1, res2);
+    let (res1, tiles) = best_paths(&maze, &costs)?;
+    if show_paths {
+        println!("{}", Highlight(&maze, &tiles));
+    }
+    println!("res1: {}, res2: {}", res1, tiles.len());
     Ok(())
 }
 
-fn astar_2(maze: &Maze) -> usize {
-    let mut queue: BinaryHeap<Reindeer> = BinaryHeap::new();
-    let mut start = maze.start.clone();
-    start.visited.insert(start.position);
-    queue.push(start);
-    let mut visited: HashMap<(Point, Direction), u32> = HashMap::new();
-    let mut best_paths: Vec<Reindeer> = Vec::new();
-    let mut min_cost: Option<u32> = None;
-
-    while let Some(next_state) = queue.pop() {
-        if maze.is_solved(&next_state) {
-            if let Some(cost) = min_cost {
-                match next_state.g_cost.cmp(&cost) {
-                    Ordering::Less => {
-                        best_paths.clear();
-                        min_cost = Some(next_state.g_cost);
-                        best_paths.push(next_state);
-                    }
-                    Ordering::Equal => {
-                        best_paths.push(next_state);
-                    }
-                    _ => {}
-                }
-            } else {
-                min_cost = Some(next_state.g_cost);
-                best_paths.push(next_state);
-            }
-            continue;
-        }
+/// Cost of moving one tile forward and of rotating by 90 degrees.
+#[derive(Debug, Clone, Copy)]
+struct Costs {
+    step: u32,
+    turn: u32,
+}
 
-        if let Some(min_cost) = min_cost {
-            if next_state.g_cost > min_cost {
-                continue;
+/// Lowest score from start to end and every tile lying on at least one path with that score.
+///
+/// A tile is on a best path when the cheapest way to reach it from the start plus the cheapest
+/// way to finish from it, in the same direction, adds up to the best score.
+fn best_paths(maze: &Maze, costs: &Costs) -> Result<(u32, HashSet<Point>)> {
+    let forward = dijkstra(maze, costs, &[(maze.start, Direction::East)]);
+    // Moves are reversible, so the cost from (p, d) to the end equals the cost of reaching
+    // (p, d.opposite()) when starting at the end facing any direction.
+    let backward = dijkstra(
+        maze,
+        costs,
+        &Direction::ALL.map(|d| (maze.target, d.opposite())),
+    );
+    let best = Direction::ALL
+        .iter()
+        .filter_map(|d| forward[maze.state_idx(&maze.target, d)])
+        .min()
+        .ok_or(AOCError::SolverError("end is not reachable".into()))?;
+    let mut tiles = HashSet::new();
+    for y in 0..=maze.max_y as i32 {
+        for x in 0..=maze.max_x as i32 {
+            let position = Point { x, y };
+            let on_path = Direction::ALL.iter().any(|d| {
+                let to = forward[maze.state_idx(&position, d)];
+                let from = backward[maze.state_idx(&position, &d.opposite())];
+                matches!((to, from), (Some(to), Some(from)) if to + from == best)
+            });
+            if on_path {
+                tiles.insert(position);
             }
         }
-        if let Some(old_cost) = visited.get(&(next_state.position, next_state.direction)) {
-            if *old_cost < next_state.g_cost {
-                continue;
-            }
-        }
-        visited.insert(
-            (next_state.position, next_state.direction),
-            next_state.g_cost,
-        );
-        if let Some(r1) = rotate(maze, &next_state, Rotation::Clockwise) {
-            queue.push(r1);
-        }
-        if let Some(r2) = rotate(maze, &next_state, Rotation::Counterclockwise) {
-            queue.push(r2);
-        }
-        if let Some(mut w) = walk(maze, &next_state) {
-            w.visited.insert(w.position);
-            queue.push(w);
-        }
     }
-    let mut all_visited = HashSet::new();
-    for p in best_paths {
-        all_visited.extend(p.visited);
-    }
-    all_visited.len()
+    Ok((best, tiles))
 }
 
-fn astar(maze: &Maze) -> u32 {
+/// Lowest cost to every (tile, direction) state reachable from any of the `sources`.
+fn dijkstra(maze: &Maze, costs: &Costs, sources: &[(Point, Direction)]) -> Vec<Option<u32>> {
+    let mut dist = vec![None; (maze.max_x + 1) * (maze.max_y + 1) * 4];
     let mut queue: BinaryHeap<Reindeer> = BinaryHeap::new();
-    queue.push(maze.start.clone());
-    let mut visited: HashMap<(Point, Direction), u32> = HashMap::new();
-    while let Some(next_state) = queue.pop() {
-        if maze.is_solved(&next_state) {
-            return next_state.g_cost;
+    for &(position, direction) in sources {
+        queue.push(Reindeer {
+            position,
+            direction,
+            cost: 0,
+        });
+    }
+    while let Some(current) = queue.pop() {
+        let idx = maze.state_idx(&current.position, &current.direction);
+        if dist[idx].is_some() {
+            continue;
         }
-        if let Some(old_cost) = visited.get(&(next_state.position, next_state.direction)) {
-            if *old_cost <= next_state.g_cost {
-                continue;
+        dist[idx] = Some(current.cost);
+        for next in current.moves(maze, costs) {
+            if dist[maze.state_idx(&next.position, &next.direction)].is_none() {
+                queue.push(next);
             }
         }
-        visited.insert(
-            (next_state.position, next_state.direction),
-            next_state.g_cost,
-        );
-        if let Some(r1) = rotate(maze, &next_state, Rotation::Clockwise) {
-            queue.push(r1);
-        }
-        if let Some(r2) = rotate(maze, &next_state, Rotation::Counterclockwise) {
-            queue.push(r2);
-        }
-        if let Some(w) = walk(maze, &next_state) {
-            queue.push(w);
-        }
-    }
-    0
-}
-
-fn rotate(maze: &Maze, reindeer: &Reindeer, rot: Rotation) -> Option<Reindeer> {
-    if reindeer.last_move == Move::Rot {
-        return None;
-    }
-    let mut r: Reindeer = match rot {
-        Rotation::Clockwise => Reindeer {
-            position: reindeer.position,
-            direction: match reindeer.direction {
-                Direction::North => Direction::East,
-                Direction::East => Direction::South,
-                Direction::South => Direction::West,
-                Direction::West => Direction::North,
-            },
-            g_cost: reindeer.g_cost + 1000,
-            h_cost: 0,
-            last_move: Move::Rot,
-            visited: reindeer.visited.clone(),
-        },
-
-        Rotation::Counterclockwise => Reindeer {
-            position: reindeer.position,
-            direction: match reindeer.direction {
-                Direction::North => Direction::West,
-                Direction::West => Direction::South,
-                Direction::South => Direction::East,
-                Direction::East => Direction::North,
-            },
-            g_cost: reindeer.g_cost + 1000,
-            h_cost: 0,
-            last_move: Move::Rot,
-            visited: reindeer.visited.clone(),
-        },
-    };
-    r.h_cost = maze.get_cost(&r);
-    Some(r)
-}
-
-fn walk(maze: &Maze, reindeer: &Reindeer) -> Option<Reindeer> {
-    let next_pos = reindeer.position.add_direction(&reindeer.direction);
-    if maze.walls.contains(&next_pos) {
-        return None;
     }
-    let mut r = Reindeer {
-        position: next_pos,
-        direction: reindeer.direction,
-        g_cost: reindeer.g_cost + 1,
-        h_cost: 0,
-        last_move: Move::Walk,
-        visited: reindeer.visited.clone(),
-    };
-    r.h_cost = maze.get_cost(&r);
-    Some(r)
-}
-
-#[derive(Debug, Clone, Default, Eq, PartialEq)]
-enum Move {
-    Walk,
-    Rot,
-    #[default]
-    None,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-enum Rotation {
-    Clockwise,
-    Counterclockwise,
+    dist
 }
 
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -188,6 +105,37 @@ enum Direction {
     West,
 }
 
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn clockwise(&self) -> Self {
+        match self {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+        }
+    }
+
+    fn counterclockwise(&self) -> Self {
+        match self {
+            Direction::North => Direction::West,
+            Direction::West => Direction::South,
+            Direction::South => Direction::East,
+            Direction::East => Direction::North,
+        }
+    }
+
+    fn opposite(&self) -> Self {
+        self.clockwise().clockwise()
+    }
+}
+
 #[derive(Hash, Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Default, Copy)]
 struct Point {
     x: i32,
@@ -231,20 +179,44 @@ impl Add for Point {
 struct Reindeer {
     position: Point,
     direction: Direction,
-    g_cost: u32,
-    h_cost: u32,
-    last_move: Move,
-    visited: HashSet<Point>,
+    cost: u32,
+}
+
+impl Reindeer {
+    /// The two rotations in place and, unless blocked by a wall, one step forward.
+    fn moves(&self, maze: &Maze, costs: &Costs) -> Vec<Reindeer> {
+        let mut moves = vec![
+            Reindeer {
+                position: self.position,
+                direction: self.direction.clockwise(),
+                cost: self.cost + costs.turn,
+            },
+            Reindeer {
+                position: self.position,
+                direction: self.direction.counterclockwise(),
+                cost: self.cost + costs.turn,
+            },
+        ];
+        let next_pos = self.position.add_direction(&self.direction);
+        if maze.is_open(&next_pos) {
+            moves.push(Reindeer {
+                position: next_pos,
+                direction: self.direction,
+                cost: self.cost + costs.step,
+            });
+        }
+        moves
+    }
 }
 
 impl Ord for Reindeer {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        (other.g_cost + other.h_cost).cmp(&(self.g_cost + self.h_cost))
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
     }
 }
 
 impl PartialOrd for Reindeer {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
@@ -253,36 +225,30 @@ impl PartialOrd for Reindeer {
 struct Maze {
     walls: HashSet<Point>,
     target: Point,
-    start: Reindeer,
+    start: Point,
     max_x: usize,
     max_y: usize,
 }
 
 impl Maze {
     fn parse(data: PathBuf) -> Result<Self> {
-        let f = fs::read(data)?;
+        Self::parse_str(&fs::read_to_string(data)?)
+    }
+
+    fn parse_str(input: &str) -> Result<Self> {
         let mut maze = Maze::default();
-        for (y, row) in f.split(|c| *c == b'\n').enumerate() {
-            for (x, tile) in row.iter().enumerate() {
+        for (y, row) in input.lines().enumerate() {
+            for (x, tile) in row.bytes().enumerate() {
+                let position = Point {
+                    x: x as i32,
+                    y: y as i32,
+                };
                 match tile {
                     b'#' => {
-                        maze.walls.insert(Point {
-                            x: x as i32,
-                            y: y as i32,
-                        });
-                    }
-                    b'E' => {
-                        maze.target = Point {
-                            x: x as i32,
-                            y: y as i32,
-                        };
-                    }
-                    b'S' => {
-                        maze.start.position = Point {
-                            x: x as i32,
-                            y: y as i32,
-                        };
+                        maze.walls.insert(position);
                     }
+                    b'E' => maze.target = position,
+                    b'S' => maze.start = position,
                     b'.' => {}
                     _ => return Err(AOCError::ParseError("could not parse maze".into())),
                 }
@@ -290,29 +256,47 @@ impl Maze {
             }
             maze.max_y = maze.max_y.max(y);
         }
-        maze.start.h_cost = maze.get_cost(&maze.start);
         Ok(maze)
     }
 
-    fn is_solved(&self, reindeer: &Reindeer) -> bool {
-        reindeer.position == self.target
+    fn is_open(&self, position: &Point) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.x as usize <= self.max_x
+            && position.y as usize <= self.max_y
+            && !self.walls.contains(position)
     }
 
-    fn get_cost(&self, reindeer: &Reindeer) -> u32 {
-        self.target.x.abs_diff(reindeer.position.x) + self.target.y.abs_diff(reindeer.position.y)
+    /// Index of the (position, direction) state, `position` must be inside the maze.
+    fn state_idx(&self, position: &Point, direction: &Direction) -> usize {
+        let tile = position.y as usize * (self.max_x + 1) + position.x as usize;
+        tile * 4 + *direction as usize
     }
 }
 
 impl Display for Maze {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for y in 0..=self.max_y {
+        write!(f, "{}", Highlight(self, &HashSet::new()))
+    }
+}
+
+/// Maze rendering with the given tiles marked as 'O'.
+struct Highlight<'a>(&'a Maze, &'a HashSet<Point>);
+
+impl Display for Highlight<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Highlight(maze, tiles) = self;
+        for y in 0..=maze.max_y {
             writeln!(f)?;
-            for x in 0..=self.max_x {
-                if self.walls.contains(&Point {
+            for x in 0..=maze.max_x {
+                let position = Point {
                     x: x as i32,
                     y: y as i32,
-                }) {
+                };
+                if maze.walls.contains(&position) {
                     write!(f, "#")?;
+                } else if tiles.contains(&position) {
+                    write!(f, "O")?;
                 } else {
                     write!(f, ".")?;
                 }
@@ -321,3 +305,56 @@ impl Display for Maze {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+#################
+#...#...#...#..E#
+#.#.#.#.#.#.#.#.#
+#.#.#.#...#...#.#
+#.#.#.#.###.#.#.#
+#...#.#.#.....#.#
+#.#.#.#.#.#####.#
+#.#...#.#.#.....#
+#.#.#####.#.###.#
+#.#.#.......#...#
+#.#.###.#####.###
+#.#.#...#.....#.#
+#.#.#.#####.###.#
+#.#.#.........#.#
+#.#.#.#########.#
+#S#.............#
+#################
+";
+
+    #[test]
+    fn example() {
+        let maze = Maze::parse_str(EXAMPLE).unwrap();
+        let costs = Costs {
+            step: 1,
+            turn: 1000,
+        };
+        let (score, tiles) = best_paths(&maze, &costs).unwrap();
+        assert_eq!(score, 11048);
+        assert_eq!(tiles.len(), 64);
+    }
+
+    #[test]
+    fn custom_costs() {
+        let maze = Maze::parse_str("#######\n#....E#\n#.###.#\n#S....#\n#######\n").unwrap();
+        let costs = Costs {
+            step: 1,
+            turn: 1000,
+        };
+        let (score, tiles) = best_paths(&maze, &costs).unwrap();
+        assert_eq!(score, 1006);
+        assert_eq!(tiles.len(), 7);
+        let costs = Costs { step: 1, turn: 0 };
+        let (score, tiles) = best_paths(&maze, &costs).unwrap();
+        assert_eq!(score, 6);
+        assert_eq!(tiles.len(), 12);
+    }
+}
@@ -1,29 +1,23 @@
 #![allow(non_camel_case_types)]
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::{fmt::Display, fs, path::PathBuf};
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
+const MAX_STEPS: usize = 1_000_000;
+const MAX_CANDIDATES: u64 = 1_000_000;
+
+pub const PARAMS: &[&str] = &["max_steps", "max_candidates"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
     let (mut cpu, stack) = parse(data)?;
+    let max_steps = params.get_or("max_steps", MAX_STEPS)?;
+    let max_candidates = params.get_or("max_candidates", MAX_CANDIDATES)?;
     if verbosity > 1 {
         println!("{}  {:#?}", cpu, stack);
     }
-    loop {
-        let next_ins = cpu.fetch_op(&stack);
-        if verbosity > 2 {
-            println!("{:#?}", next_ins);
-        }
-        if next_ins == Instruction::halt {
-            break;
-        }
-        cpu.execute_op(next_ins);
-        if verbosity > 3 {
-            println!("{}", cpu);
-        }
-    }
-    cpu.flush();
+    let res1 = cpu.run(&stack, max_steps, verbosity)?;
+    println!("res1: {}", res1);
     cpu.reset();
-    //let res2 = get_a(&mut cpu, &stack);
-    let res2 = get_a(&mut cpu, &stack);
+    let res2 = get_a(&mut cpu, &stack, max_steps, max_candidates)?;
     println!("res2: {}", res2);
     Ok(())
 }
@@ -70,54 +64,43 @@ fn prog_from_ins(stack: &Stack) -> Vec<u64> {
     prog
 }
 
-fn get_a(cpu: &mut Cpu, stack: &Stack) -> u64 {
-    loop {
-        let next_ins = cpu.fetch_op(stack);
-        if next_ins == Instruction::halt {
-            break;
-        }
-        cpu.execute_op(next_ins);
-    }
+/// Searches the value of register A that makes the program output itself.
+/// Fails if the output never gets as long as the program or after trying `max_candidates`
+/// values of A.
+fn get_a(cpu: &mut Cpu, stack: &Stack, max_steps: usize, max_candidates: u64) -> Result<u64> {
     let prog = prog_from_ins(stack);
-    let mut a = 1;
+    let mut a: u64 = 1;
     loop {
         cpu.reset();
         cpu.register_a = a;
-        loop {
-            let next_ins = cpu.fetch_op(stack);
-            if next_ins == Instruction::halt {
-                break;
-            }
-            cpu.execute_op(next_ins);
-        }
-        if cpu.out_buf.len() < prog.len() {
-            a *= 2;
+        if cpu.run(stack, max_steps, 0)?.len() < prog.len() {
+            a = a.checked_mul(2).ok_or(AOCError::SolverError(
+                "no quine found, the output never gets as long as the program".into(),
+            ))?;
         } else {
             break;
         }
     }
-    println!("{}", a);
+    let mut candidates = 0;
     for i in 1..=prog.len() {
         loop {
             cpu.reset();
             cpu.register_a = a;
-            loop {
-                let next_ins = cpu.fetch_op(stack);
-                if next_ins == Instruction::halt {
-                    break;
-                }
-                cpu.execute_op(next_ins);
-            }
-            //println!("o: {:#?}", &prog[..=i]);
-            //println!("b: {:#?}", cpu.out_buf);
-            //break;
-            if prog[prog.len() - i..] == cpu.out_buf {
+            if prog[prog.len() - i..] == cpu.run(stack, max_steps, 0)?.0 {
                 break;
             }
-            a += 1;
+            candidates += 1;
+            a = match a.checked_add(1) {
+                Some(a) if candidates < max_candidates => a,
+                _ => {
+                    return Err(AOCError::SolverError(format!(
+                        "no quine found within {max_candidates} candidates for register A"
+                    )));
+                }
+            };
         }
     }
-    a / 2
+    Ok(a / 2)
 }
 
 #[derive(Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Debug)]
@@ -135,6 +118,29 @@ enum Instruction {
 
 type Stack = Vec<Instruction>;
 
+/// Values emitted by `out` instructions during a run.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+struct Output(Vec<u64>);
+
+impl Output {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut vals = self.0.iter();
+        if let Some(first) = vals.next() {
+            write!(f, "{first}")?;
+        }
+        for val in vals {
+            write!(f, ",{val}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Default, Debug)]
 struct Cpu {
     stack_pointer: usize,
@@ -146,10 +152,31 @@ struct Cpu {
 }
 
 impl Cpu {
-    fn execute_op(&mut self, instruction: Instruction) {
+    /// Runs until the program halts and returns its output.
+    /// Fails if `max_steps` instructions were executed without halting.
+    fn run(&mut self, stack: &Stack, max_steps: usize, verbosity: u8) -> Result<Output> {
+        for _ in 0..max_steps {
+            let next_ins = self.fetch_op(stack);
+            if verbosity > 2 {
+                println!("{:#?}", next_ins);
+            }
+            if next_ins == Instruction::halt {
+                return Ok(Output(std::mem::take(&mut self.out_buf)));
+            }
+            self.execute_op(next_ins, stack.len())?;
+            if verbosity > 3 {
+                println!("{}", self);
+            }
+        }
+        Err(AOCError::SolverError(format!(
+            "program did not halt within {max_steps} steps"
+        )))
+    }
+
+    fn execute_op(&mut self, instruction: Instruction, stack_len: usize) -> Result<()> {
         match instruction {
             Instruction::adv(op) => {
-                self.register_a /= 2_u64.pow(self.combo_op(op) as u32);
+                self.register_a = self.dv(op)?;
                 self.stack_pointer += 1;
             }
             Instruction::bxl(op) => {
@@ -157,11 +184,17 @@ impl Cpu {
                 self.stack_pointer += 1;
             }
             Instruction::bst(op) => {
-                self.register_b = self.combo_op(op) % 8;
+                self.register_b = self.combo_op(op)? % 8;
                 self.stack_pointer += 1;
             }
             Instruction::jnz(op) => {
                 if self.register_a != 0 {
+                    if op % 2 != 0 || op as usize / 2 > stack_len {
+                        return Err(AOCError::SolverError(format!(
+                            "jump to {op} out of range (program length {})",
+                            stack_len * 2
+                        )));
+                    }
                     self.stack_pointer = op as usize / 2;
                 } else {
                     self.stack_pointer += 1;
@@ -172,19 +205,20 @@ impl Cpu {
                 self.stack_pointer += 1;
             }
             Instruction::out(op) => {
-                self.out_buf.push(self.combo_op(op) % 8);
+                self.out_buf.push(self.combo_op(op)? % 8);
                 self.stack_pointer += 1;
             }
             Instruction::bdv(op) => {
-                self.register_b = self.register_a / 2_u64.pow(self.combo_op(op) as u32);
+                self.register_b = self.dv(op)?;
                 self.stack_pointer += 1;
             }
             Instruction::cdv(op) => {
-                self.register_c = self.register_a / 2_u64.pow(self.combo_op(op) as u32);
+                self.register_c = self.dv(op)?;
                 self.stack_pointer += 1;
             }
             Instruction::halt => {}
         }
+        Ok(())
     }
 
     fn fetch_op(&self, stack: &Stack) -> Instruction {
@@ -194,36 +228,32 @@ impl Cpu {
         Instruction::halt
     }
 
-    fn combo_op(&self, op: u8) -> u64 {
-        match op {
-            0..=3 => op as u64,
-            4 => self.register_a,
-            5 => self.register_b,
-            6 => self.register_c,
-            _ => {
-                println!("invalid op ecncountered");
-                0
-            }
-        }
+    /// register A divided by 2^combo
+    fn dv(&self, op: u8) -> Result<u64> {
+        let exp = self.combo_op(op)?;
+        Ok(if exp >= u64::BITS as u64 {
+            0
+        } else {
+            self.register_a >> exp
+        })
     }
 
-    fn flush(&mut self) {
-        self.out_buf.clear();
-        if self.out_buf.is_empty() {
-            return;
-        }
-        if self.out_buf.len() > 1 {
-            for val in &self.out_buf[..self.out_buf.len() - 1] {
-                print!("{val},");
-            }
+    fn combo_op(&self, op: u8) -> Result<u64> {
+        match op {
+            0..=3 => Ok(op as u64),
+            4 => Ok(self.register_a),
+            5 => Ok(self.register_b),
+            6 => Ok(self.register_c),
+            _ => Err(AOCError::SolverError(format!(
+                "invalid combo operand {op} at {}",
+                self.stack_pointer * 2
+            ))),
         }
-        print!("{}", self.out_buf[self.out_buf.len() - 1]);
-        self.out_buf.clear();
     }
 
     fn reset(&mut self) {
         self.stack_pointer = 0;
-        self.flush();
+        self.out_buf.clear();
         self.register_b = 0;
         self.register_c = 0;
         self.register_a = self.original_a;
@@ -300,3 +330,68 @@ fn parse(data: PathBuf) -> Result<(Cpu, Stack)> {
     }
     Err(AOCError::ParseError("could not parse input".into()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu(a: u64) -> Cpu {
+        Cpu {
+            register_a: a,
+            original_a: a,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn example_output() {
+        let stack = vec![
+            Instruction::adv(1),
+            Instruction::out(4),
+            Instruction::jnz(0),
+        ];
+        let out = cpu(729).run(&stack, MAX_STEPS, 0).unwrap();
+        assert_eq!(out.to_string(), "4,6,3,5,6,3,5,2,1,0");
+    }
+
+    #[test]
+    fn invalid_combo_operand() {
+        let stack = vec![Instruction::out(7)];
+        assert!(matches!(
+            cpu(1).run(&stack, MAX_STEPS, 0),
+            Err(AOCError::SolverError(_))
+        ));
+    }
+
+    #[test]
+    fn jump_out_of_range() {
+        let stack = vec![Instruction::jnz(6)];
+        assert!(cpu(1).run(&stack, MAX_STEPS, 0).is_err());
+    }
+
+    #[test]
+    fn no_quine() {
+        // never outputs anything
+        let stack = vec![Instruction::bxl(7)];
+        assert!(matches!(
+            get_a(&mut cpu(0), &stack, MAX_STEPS, MAX_CANDIDATES),
+            Err(AOCError::SolverError(_))
+        ));
+        // outputs enough values, but never itself
+        let stack = vec![
+            Instruction::adv(1),
+            Instruction::out(4),
+            Instruction::jnz(0),
+        ];
+        assert!(matches!(
+            get_a(&mut cpu(729), &stack, MAX_STEPS, 1000),
+            Err(AOCError::SolverError(_))
+        ));
+    }
+
+    #[test]
+    fn step_limit() {
+        let stack = vec![Instruction::jnz(0)];
+        assert!(cpu(1).run(&stack, 100, 0).is_err());
+    }
+}
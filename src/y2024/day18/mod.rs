@@ -1,4 +1,4 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::{
     collections::{BinaryHeap, HashMap, HashSet},
     fmt::Display,
@@ -11,19 +11,46 @@ const XBOUNDS: i32 = 70;
 const YBOUNDS: i32 = 70;
 const BYTES: u32 = 1024;
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["x_bounds", "y_bounds", "bytes", "render"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
     let grid = parse(data)?;
+    let config = Config {
+        x_bounds: params.get_or("x_bounds", XBOUNDS)?,
+        y_bounds: params.get_or("y_bounds", YBOUNDS)?,
+        bytes: params.get_or("bytes", BYTES)?,
+    };
     if verbosity > 1 {
-        print_grid(&grid, &State::default());
+        print_grid(&grid, &config, config.bytes, &State::default(), None);
         println!();
     }
-    let res = astar(&grid, verbosity, BYTES);
-    let res2 = part2(&grid, verbosity);
+    let res = astar(&grid, &config, verbosity, config.bytes)
+        .ok_or(AOCError::SolverError("exit is not reachable".into()))?
+        .g_cost;
+    // a partial byte list may never cut off the exit, part 1 still has an answer then
+    let res2 = match part2(&grid, &config) {
+        Some((res2, path)) => {
+            if params.flag("render")? || verbosity > 1 {
+                print_grid(&grid, &config, grid[&res2], &path, Some(res2));
+                println!();
+            }
+            res2.to_string()
+        }
+        None => "none".into(),
+    };
     println!();
     println!("res1: {}, res2: {}", res, res2);
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    x_bounds: i32,
+    y_bounds: i32,
+    /// number of fallen bytes for part 1
+    bytes: u32,
+}
+
 #[derive(Hash, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 struct Point {
     x: i32,
@@ -42,7 +69,7 @@ impl Add for Point {
 
 impl Display for Point {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "{},{}", self.x, self.y)
+        write!(f, "{},{}", self.x, self.y)
     }
 }
 
@@ -69,67 +96,73 @@ impl PartialOrd for State {
     }
 }
 
-fn part2(grid: &Grid, verbosity: u8) -> Point {
-    let mut bytes = grid.len() as u32 - 1;
-    while bytes > 0 {
-        if astar(grid, verbosity, bytes) != 0 {
-            break;
-        }
-        bytes -= 1;
+/// Binary searches the number of fallen bytes for the first one cutting off the exit.
+/// Returns that byte together with the last path that still reached the exit.
+fn part2(grid: &Grid, config: &Config) -> Option<(Point, State)> {
+    let mut reachable = 0;
+    let mut blocked = grid.len() as u32;
+    if astar(grid, config, 0, blocked).is_some() {
+        return None;
     }
-    for (k, v) in grid {
-        if *v == bytes {
-            return *k;
+    while blocked - reachable > 1 {
+        let mid = reachable + (blocked - reachable) / 2;
+        if astar(grid, config, 0, mid).is_some() {
+            reachable = mid;
+        } else {
+            blocked = mid;
         }
     }
-    Point { x: 0, y: 0 }
+    let path = astar(grid, config, 0, reachable)?;
+    grid.iter()
+        .find(|(_, v)| **v == blocked - 1)
+        .map(|(k, _)| (*k, path))
 }
 
-fn astar(grid: &Grid, verbosity: u8, bytes: u32) -> u32 {
+fn astar(grid: &Grid, config: &Config, verbosity: u8, bytes: u32) -> Option<State> {
     let mut queue: BinaryHeap<State> = BinaryHeap::new();
     queue.push(State {
         pos: Point { x: 0, y: 0 },
         g_cost: 0,
-        h_cost: get_cost(&Point { x: 0, y: 0 }),
+        h_cost: get_cost(&Point { x: 0, y: 0 }, config),
         last_move: Point { x: 0, y: 0 },
         visited: HashSet::from([Point { x: 0, y: 0 }]),
     });
     let mut visited: HashMap<Point, u32> = HashMap::new();
 
     while let Some(next) = queue.pop() {
-        if next.pos.x == XBOUNDS && next.pos.y == YBOUNDS {
+        if next.pos.x == config.x_bounds && next.pos.y == config.y_bounds {
             if verbosity > 2 {
-                print_grid(grid, &next);
+                print_grid(grid, config, bytes, &next, None);
             }
-            return next.g_cost;
+            return Some(next);
         }
-        if let Some(old) = visited.get(&next.pos) {
-            if *old <= next.g_cost + next.h_cost {
-                continue;
-            }
+        if let Some(old) = visited.get(&next.pos)
+            && *old <= next.g_cost + next.h_cost
+        {
+            continue;
         }
         visited
             .entry(next.pos)
             .and_modify(|e| *e = next.g_cost + next.h_cost)
             .or_insert(next.g_cost + next.h_cost);
-        if let Some(next_s) = next_up(&next, grid, bytes) {
+        if let Some(next_s) = next_up(&next, grid, config, bytes) {
             queue.push(next_s);
         }
-        if let Some(next_s) = next_down(&next, grid, bytes) {
+        if let Some(next_s) = next_down(&next, grid, config, bytes) {
             queue.push(next_s);
         }
-        if let Some(next_s) = next_left(&next, grid, bytes) {
+        if let Some(next_s) = next_left(&next, grid, config, bytes) {
             queue.push(next_s);
         }
-        if let Some(next_s) = next_right(&next, grid, bytes) {
+        if let Some(next_s) = next_right(&next, grid, config, bytes) {
             queue.push(next_s);
         }
     }
 
-    0
+    None
 }
 
-fn next_up(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
+fn next_up(state: &State, grid: &Grid, config: &Config, bytes: u32) -> Option<State> {
     if state.pos.y == 0 {
         return None;
     }
@@ -138,24 +171,24 @@ fn next_up(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
     if state.last_move == down {
         return None;
     }
-    if let Some(byte) = grid.get(&(state.pos + up)) {
-        if *byte < bytes {
-            return None;
-        }
+    if let Some(byte) = grid.get(&(state.pos + up))
+        && *byte < bytes
+    {
+        return None;
     }
     let mut next = state.visited.clone();
     next.insert(state.pos + up);
     Some(State {
         pos: state.pos + up,
         g_cost: state.g_cost + 1,
-        h_cost: get_cost(&(state.pos + up)),
+        h_cost: get_cost(&(state.pos + up), config),
         last_move: up,
         visited: next,
     })
 }
 
-fn next_down(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
-    if state.pos.y == YBOUNDS {
+fn next_down(state: &State, grid: &Grid, config: &Config, bytes: u32) -> Option<State> {
+    if state.pos.y == config.y_bounds {
         return None;
     }
     let down = Point { x: 0, y: 1 };
@@ -163,23 +196,23 @@ fn next_down(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
     if state.last_move == up {
         return None;
     }
-    if let Some(byte) = grid.get(&(state.pos + down)) {
-        if *byte < bytes {
-            return None;
-        }
+    if let Some(byte) = grid.get(&(state.pos + down))
+        && *byte < bytes
+    {
+        return None;
     }
     let mut next = state.visited.clone();
     next.insert(state.pos + down);
     Some(State {
         pos: state.pos + down,
         g_cost: state.g_cost + 1,
-        h_cost: get_cost(&(state.pos + down)),
+        h_cost: get_cost(&(state.pos + down), config),
         last_move: down,
         visited: next,
     })
 }
-fn next_right(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
-    if state.pos.x == XBOUNDS {
+fn next_right(state: &State, grid: &Grid, config: &Config, bytes: u32) -> Option<State> {
+    if state.pos.x == config.x_bounds {
         return None;
     }
     let left = Point { x: -1, y: 0 };
@@ -187,22 +220,22 @@ fn next_right(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
     if state.last_move == left {
         return None;
     }
-    if let Some(byte) = grid.get(&(state.pos + right)) {
-        if *byte < bytes {
-            return None;
-        }
+    if let Some(byte) = grid.get(&(state.pos + right))
+        && *byte < bytes
+    {
+        return None;
     }
     let mut next = state.visited.clone();
     next.insert(state.pos + right);
     Some(State {
         pos: state.pos + right,
         g_cost: state.g_cost + 1,
-        h_cost: get_cost(&(state.pos + right)),
+        h_cost: get_cost(&(state.pos + right), config),
         last_move: right,
         visited: next,
     })
 }
-fn next_left(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
+fn next_left(state: &State, grid: &Grid, config: &Config, bytes: u32) -> Option<State> {
     if state.pos.x == 0 {
         return None;
     }
@@ -211,34 +244,36 @@ fn next_left(state: &State, grid: &Grid, bytes: u32) -> Option<State> {
     if state.last_move == right {
         return None;
     }
-    if let Some(byte) = grid.get(&(state.pos + left)) {
-        if *byte < bytes {
-            return None;
-        }
+    if let Some(byte) = grid.get(&(state.pos + left))
+        && *byte < bytes
+    {
+        return None;
     }
     let mut next = state.visited.clone();
     next.insert(state.pos + left);
     Some(State {
         pos: state.pos + left,
         g_cost: state.g_cost + 1,
-        h_cost: get_cost(&(state.pos + left)),
+        h_cost: get_cost(&(state.pos + left), config),
         last_move: left,
         visited: next,
     })
 }
 
-fn get_cost(point: &Point) -> u32 {
-    point.x.abs_diff(XBOUNDS) + point.y.abs_diff(YBOUNDS)
+fn get_cost(point: &Point, config: &Config) -> u32 {
+    point.x.abs_diff(config.x_bounds) + point.y.abs_diff(config.y_bounds)
 }
 
-fn print_grid(grid: &Grid, state: &State) {
-    for y in 0..=YBOUNDS {
+fn print_grid(grid: &Grid, config: &Config, bytes: u32, state: &State, marker: Option<Point>) {
+    for y in 0..=config.y_bounds {
         println!();
-        for x in 0..=XBOUNDS {
-            if state.visited.contains(&Point { x, y }) {
+        for x in 0..=config.x_bounds {
+            if marker == Some(Point { x, y }) {
+                print!("X");
+            } else if state.visited.contains(&Point { x, y }) {
                 print!("O");
             } else if let Some(byte) = grid.get(&Point { x, y }) {
-                if *byte < BYTES {
+                if *byte < bytes {
                     print!("#");
                 } else {
                     print!(".");
@@ -251,7 +286,10 @@ fn print_grid(grid: &Grid, state: &State) {
 }
 
 fn parse(data: PathBuf) -> Result<Grid> {
-    let f = fs::read_to_string(data)?;
+    parse_str(&fs::read_to_string(data)?)
+}
+
+fn parse_str(f: &str) -> Result<Grid> {
     f.lines()
         .enumerate()
         .map(|(i, line)| {
@@ -272,3 +310,61 @@ fn parse(data: PathBuf) -> Result<Grid> {
         })
         .collect::<Result<Grid>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+5,4
+4,2
+4,5
+3,0
+2,1
+6,3
+2,4
+1,5
+0,6
+3,3
+2,6
+5,1
+1,2
+5,5
+2,5
+6,5
+1,4
+0,4
+6,4
+1,1
+6,1
+1,0
+0,5
+1,6
+2,0
+";
+
+    #[test]
+    fn example() {
+        let grid = parse_str(EXAMPLE).unwrap();
+        let config = Config {
+            x_bounds: 6,
+            y_bounds: 6,
+            bytes: 12,
+        };
+        assert_eq!(astar(&grid, &config, 0, config.bytes).unwrap().g_cost, 22);
+        let (byte, _) = part2(&grid, &config).unwrap();
+        assert_eq!(byte.to_string(), "6,1");
+    }
+
+    #[test]
+    fn exit_never_cut_off() {
+        let grid = parse_str(&EXAMPLE.lines().take(12).collect::<Vec<_>>().join("\n")).unwrap();
+        let config = Config {
+            x_bounds: 6,
+            y_bounds: 6,
+            bytes: 12,
+        };
+        assert_eq!(astar(&grid, &config, 0, config.bytes).unwrap().g_cost, 22);
+        assert!(part2(&grid, &config).is_none());
+    }
+}
@@ -1,125 +1,254 @@
-use super::{AOCError, Result};
-use std::{collections::HashMap, fs, path::PathBuf};
+use super::{AOCError, Params, Result};
+use std::{fs, path::PathBuf};
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["design", "all"];
+
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
     let (avail, designs) = parse(data)?;
-    let res1 = n_valid(&avail, &designs);
-    println!("r1: {}", res1);
-    let res2 = num_patterns(&avail, &designs);
+    let design = params
+        .get::<String>("design")?
+        .map(|design| parse_design(&design))
+        .transpose()?;
+    let trie = Trie::new(&avail);
+    let counts = designs
+        .iter()
+        .map(|design| trie.count_arrangements(design))
+        .collect::<Vec<u64>>();
+    let res1 = counts.iter().filter(|c| **c > 0).count();
+    let res2 = counts.iter().sum::<u64>();
     println!("res1: {}, res2: {}", res1, res2);
+    if let Some(design) = design {
+        let all = params.flag("all")?;
+        let mut found = 0;
+        trie.decompositions(&avail, &design, &mut |towels| {
+            let towels = towels.iter().map(|t| format_towel(t)).collect::<Vec<_>>();
+            println!("{}", towels.join(","));
+            found += 1;
+            all
+        });
+        if found == 0 {
+            println!("{} is impossible", format_towel(&design));
+        }
+    }
     Ok(())
 }
 
-fn n_valid(avail: &TowelStack, patterns: &TowelStack) -> usize {
-    patterns
-        .iter()
-        .map(|p| is_valid_pattern(avail, p, 0) as usize)
-        .sum()
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Clone, Copy, Hash)]
+enum Stripe {
+    White,
+    Blue,
+    Black,
+    Red,
+    Green,
 }
 
-fn num_patterns(avail: &TowelStack, patterns: &TowelStack) -> usize {
-    let mut stored: HashMap<(usize, Towel), usize> = HashMap::new();
-    let mut tot = 0;
-    for towel in patterns.iter() {
-        tot += get_valid_patterns(avail, towel, 0, &mut stored);
+impl Stripe {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'r' => Some(Stripe::Red),
+            'w' => Some(Stripe::White),
+            'u' => Some(Stripe::Blue),
+            'b' => Some(Stripe::Black),
+            'g' => Some(Stripe::Green),
+            _ => None,
+        }
+    }
+
+    fn as_char(&self) -> char {
+        match self {
+            Stripe::Red => 'r',
+            Stripe::White => 'w',
+            Stripe::Blue => 'u',
+            Stripe::Black => 'b',
+            Stripe::Green => 'g',
+        }
     }
+}
 
-    tot
+type Towel = Vec<Stripe>;
+type TowelStack = Vec<Towel>;
+
+#[derive(Default, Debug)]
+struct TrieNode {
+    children: [Option<usize>; 5],
+    /// index of the available towel ending here
+    towel: Option<usize>,
 }
 
-fn get_valid_patterns(
-    avail: &TowelStack,
-    pattern: &Towel,
-    current_stripe: usize,
-    stored_patterns: &mut HashMap<(usize, Towel), usize>,
-) -> usize {
-    if current_stripe == pattern.len() {
-        return 1;
-    }
-    let key = (current_stripe, pattern[current_stripe..].to_vec());
-    if let Some(&cached_result) = stored_patterns.get(&key) {
-        return cached_result;
-    }
+/// Prefix tree over the available towels.
+#[derive(Debug)]
+struct Trie {
+    nodes: Vec<TrieNode>,
+}
 
-    let mut tot = 0;
-    for a in avail.iter() {
-        if a.len() + current_stripe > pattern.len() {
-            continue;
-        }
-        if pattern[current_stripe..current_stripe + a.len()] == a[..] {
-            tot += get_valid_patterns(avail, pattern, current_stripe + a.len(), stored_patterns);
+impl Trie {
+    fn new(avail: &TowelStack) -> Self {
+        let mut trie = Trie {
+            nodes: vec![TrieNode::default()],
+        };
+        for (i, towel) in avail.iter().enumerate() {
+            let mut node = 0;
+            for stripe in towel {
+                node = match trie.nodes[node].children[*stripe as usize] {
+                    Some(next) => next,
+                    None => {
+                        trie.nodes.push(TrieNode::default());
+                        let next = trie.nodes.len() - 1;
+                        trie.nodes[node].children[*stripe as usize] = Some(next);
+                        next
+                    }
+                };
+            }
+            if !towel.is_empty() {
+                trie.nodes[node].towel = Some(i);
+            }
         }
+        trie
     }
 
-    stored_patterns.insert(key, tot);
-    tot
-}
+    /// Available towels matching the design at `start`, as (towel index, end).
+    fn matches<'a>(
+        &'a self,
+        design: &'a [Stripe],
+        start: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let mut node = Some(0);
+        design[start..]
+            .iter()
+            .enumerate()
+            .map_while(move |(i, stripe)| {
+                node = self.nodes[node?].children[*stripe as usize];
+                Some((node?, start + i + 1))
+            })
+            .filter_map(|(node, end)| self.nodes[node].towel.map(|towel| (towel, end)))
+    }
 
-fn is_valid_pattern(avail: &TowelStack, pattern: &Towel, current_stripe: usize) -> bool {
-    if current_stripe == pattern.len() {
-        return true;
+    /// Left to right dp where ways[i] is the number of arrangements of the first i stripes.
+    fn count_arrangements(&self, design: &Towel) -> u64 {
+        let mut ways = vec![0_u64; design.len() + 1];
+        ways[0] = 1;
+        for start in 0..design.len() {
+            if ways[start] == 0 {
+                continue;
+            }
+            for (_, end) in self.matches(design, start) {
+                ways[end] += ways[start];
+            }
+        }
+        ways[design.len()]
     }
-    for a in avail {
-        if a.len() + current_stripe > pattern.len() {
-            continue;
+
+    /// Calls `found` with every decomposition of the design until it returns false.
+    fn decompositions(
+        &self,
+        avail: &TowelStack,
+        design: &Towel,
+        found: &mut dyn FnMut(&[&Towel]) -> bool,
+    ) {
+        // possible[i]: the stripes from i onwards can be arranged, prunes dead ends
+        let mut possible = vec![false; design.len() + 1];
+        possible[design.len()] = true;
+        for start in (0..design.len()).rev() {
+            possible[start] = self.matches(design, start).any(|(_, end)| possible[end]);
         }
-        if pattern[current_stripe..current_stripe + a.len()] == a[..]
-            && is_valid_pattern(avail, pattern, current_stripe + a.len())
-        {
+        let mut current = Vec::new();
+        self.decompose(avail, design, 0, &possible, &mut current, found);
+    }
+
+    fn decompose<'a>(
+        &self,
+        avail: &'a TowelStack,
+        design: &Towel,
+        start: usize,
+        possible: &[bool],
+        current: &mut Vec<&'a Towel>,
+        found: &mut dyn FnMut(&[&Towel]) -> bool,
+    ) -> bool {
+        if !possible[start] {
             return true;
         }
+        if start == design.len() {
+            return found(current);
+        }
+        for (towel, end) in self.matches(design, start) {
+            current.push(&avail[towel]);
+            let go_on = self.decompose(avail, design, end, possible, current, found);
+            current.pop();
+            if !go_on {
+                return false;
+            }
+        }
+        true
     }
-    false
 }
 
-#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Clone, Hash)]
-enum Stripe {
-    White,
-    Blue,
-    Black,
-    Red,
-    Green,
+fn parse_towel(s: &str) -> Towel {
+    s.chars().filter_map(Stripe::from_char).collect()
 }
 
-type Towel = Vec<Stripe>;
-type TowelStack = Vec<Towel>;
+/// Parses a design given on the command line, where a typo should not be skipped silently.
+fn parse_design(s: &str) -> Result<Towel> {
+    if s.is_empty() {
+        return Err(AOCError::ParseError("empty design".into()));
+    }
+    s.chars()
+        .map(|c| {
+            Stripe::from_char(c).ok_or(AOCError::ParseError(format!(
+                "stripe {c} in design {s}, expected one of w, u, b, r, g"
+            )))
+        })
+        .collect()
+}
+
+fn format_towel(towel: &Towel) -> String {
+    towel.iter().map(Stripe::as_char).collect()
+}
 
 fn parse(data: PathBuf) -> Result<(TowelStack, TowelStack)> {
     let f = fs::read_to_string(data)?;
+    parse_str(&f)
+}
+
+fn parse_str(f: &str) -> Result<(TowelStack, TowelStack)> {
     let mut f = f.lines();
     let avail = if let (Some(avail), Some(_)) = (f.next(), f.next()) {
-        Ok(avail
-            .split(',')
-            .map(|pattern| {
-                pattern
-                    .chars()
-                    .filter_map(|c| match c {
-                        'r' => Some(Stripe::Red),
-                        'w' => Some(Stripe::White),
-                        'u' => Some(Stripe::Blue),
-                        'b' => Some(Stripe::Black),
-                        'g' => Some(Stripe::Green),
-                        _ => None,
-                    })
-                    .collect::<Towel>()
-            })
-            .collect::<TowelStack>())
+        Ok(avail.split(',').map(parse_towel).collect::<TowelStack>())
     } else {
         Err(AOCError::ParseError("too few lines".into()))
     }?;
     let designs = f
-        .map(|line| {
-            line.chars()
-                .filter_map(|c| match c {
-                    'r' => Some(Stripe::Red),
-                    'w' => Some(Stripe::White),
-                    'u' => Some(Stripe::Blue),
-                    'b' => Some(Stripe::Black),
-                    'g' => Some(Stripe::Green),
-                    _ => None,
-                })
-                .collect::<Towel>()
-        })
+        .filter(|line| !line.is_empty())
+        .map(parse_towel)
         .collect::<TowelStack>();
     Ok((avail, designs))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let (avail, designs) = parse_str("r, wr, b, g, bwu, rb, gb, br\n\nbrwrr\nbggr\ngbbr\nrrbgbr\nubwu\nbwurrg\nbrgr\nbbrgwb\n").unwrap();
+        let trie = Trie::new(&avail);
+        let counts = designs
+            .iter()
+            .map(|d| trie.count_arrangements(d))
+            .collect::<Vec<u64>>();
+        assert_eq!(counts, vec![2, 1, 4, 6, 0, 1, 2, 0]);
+        let mut all = Vec::new();
+        trie.decompositions(&avail, &designs[0], &mut |towels| {
+            all.push(towels.iter().map(|t| format_towel(t)).collect::<Vec<_>>());
+            true
+        });
+        assert_eq!(all, vec![vec!["b", "r", "wr", "r"], vec!["br", "wr", "r"]]);
+    }
+
+    #[test]
+    fn design_param() {
+        assert_eq!(parse_design("brwrr").unwrap(), parse_towel("brwrr"));
+        assert!(parse_design("brXwrr").is_err());
+        assert!(parse_design("xyz").is_err());
+        assert!(parse_design("").is_err());
+    }
+}
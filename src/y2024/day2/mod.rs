@@ -1,73 +1,85 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::fs;
 use std::path::PathBuf;
 
 type Array = Vec<Vec<u64>>;
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["removed"];
+
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
     let data = load_data(data)?;
+    if params.flag("removed")? {
+        for (n, report) in data.iter().enumerate() {
+            match check(report, true) {
+                Safety::SafeWithout(i) => {
+                    println!("report {n}: removed level {i} ({})", report[i])
+                }
+                Safety::Unsafe => println!("report {n}: unsafe"),
+                Safety::Safe => {}
+            }
+        }
+    }
     let res1 = safe_recs(&data);
     let res2 = safe_recs_with_damp(&data);
     println!("res1: {}, res2: {}", res1, res2);
     Ok(())
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum Safety {
+    Safe,
+    /// safe once the level at this index is removed
+    SafeWithout(usize),
+    Unsafe,
+}
+
 fn safe_recs_with_damp(data: &Array) -> u64 {
-    let mut tot = 0;
-    for report in data {
-        if report.is_empty() {
-            tot += 1;
-            continue;
-        }
-        for rem in 0..report.len() {
-            let mut is_valid = true;
-            let mut rec = report.clone();
-            rec.remove(rem);
-            if rec.len() < 2 {
-                tot += 1;
-                break;
-            }
-            let last_dec = rec.first() > rec.get(1);
-            for levels in rec.windows(2) {
-                if let [p1, p2, ..] = levels {
-                    if !(1..=3).contains(&p1.abs_diff(*p2)) || ((p1 > p2) != last_dec) {
-                        is_valid = false;
-                        break;
-                    }
-                }
-            }
-            if is_valid {
-                tot += 1;
-                break;
-            }
-        }
-    }
-    tot
+    data.iter()
+        .filter(|report| check(report, true) != Safety::Unsafe)
+        .count() as u64
 }
 
 fn safe_recs(data: &Array) -> u64 {
-    let mut tot = 0;
-    for report in data {
-        let mut is_valid = (1..=3).contains(
-            &report
-                .first()
-                .expect("well shit")
-                .abs_diff(*report.get(1).expect("crap")),
-        );
-        let is_decreasing = report.first() > report.get(1);
-        for levels in report[1..].windows(2) {
-            if let [p1, p2, ..] = levels {
-                if !(1..=3).contains(&p1.abs_diff(*p2)) || ((p2 < p1) != is_decreasing) {
-                    is_valid = false;
-                    break;
-                }
-            }
+    data.iter()
+        .filter(|report| check(report, false) == Safety::Safe)
+        .count() as u64
+}
+
+/// Classifies a report, allowing one level to be removed when `dampener` is set.
+///
+/// Any removal that fixes a report must drop one of the two levels of its first bad step, so
+/// each direction needs at most three linear scans.
+fn check(report: &[u64], dampener: bool) -> Safety {
+    let mut removal = None;
+    for increasing in [true, false] {
+        let Some((a, b)) = first_bad_step(report, increasing, None) else {
+            return Safety::Safe;
+        };
+        if dampener && removal.is_none() {
+            removal = [a, b]
+                .into_iter()
+                .find(|&skip| first_bad_step(report, increasing, Some(skip)).is_none());
         }
-        if is_valid {
-            tot += 1;
+    }
+    match removal {
+        Some(i) => Safety::SafeWithout(i),
+        None => Safety::Unsafe,
+    }
+}
+
+/// Indices of the first pair of consecutive levels, ignoring `skip`, that is not a step of 1 to
+/// 3 in the given direction.
+fn first_bad_step(report: &[u64], increasing: bool, skip: Option<usize>) -> Option<(usize, usize)> {
+    let mut levels = (0..report.len()).filter(|&i| Some(i) != skip);
+    let mut prev = levels.next()?;
+    for i in levels {
+        let (p1, p2) = (report[prev], report[i]);
+        if !(1..=3).contains(&p1.abs_diff(p2)) || ((p2 > p1) != increasing) {
+            return Some((prev, i));
         }
+        prev = i;
     }
-    tot
+    None
 }
 
 fn load_data(data: PathBuf) -> Result<Array> {
@@ -155,4 +167,18 @@ mod tests {
         ];
         assert_eq!(safe_recs_with_damp(&data), 4);
     }
+    #[test]
+    fn test_single_level() {
+        let data = vec![vec![], vec![7], vec![1, 9]];
+        assert_eq!(safe_recs(&data), 2);
+        assert_eq!(safe_recs_with_damp(&data), 3);
+    }
+    #[test]
+    fn test_removed_level() {
+        assert_eq!(check(&[7, 6, 4, 2, 1], true), Safety::Safe);
+        assert_eq!(check(&[1, 3, 2, 4, 5], true), Safety::SafeWithout(1));
+        assert_eq!(check(&[8, 6, 4, 4, 1], true), Safety::SafeWithout(2));
+        assert_eq!(check(&[1, 2, 7, 8, 9], true), Safety::Unsafe);
+        assert_eq!(check(&[1, 3, 2, 4, 5], false), Safety::Unsafe);
+    }
 }
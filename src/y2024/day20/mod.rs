@@ -1,206 +1,97 @@
 use std::{
-    collections::{BinaryHeap, HashMap, HashSet},
+    collections::{BTreeMap, HashSet, VecDeque},
     fs,
     ops::{Add, Sub},
     path::PathBuf,
 };
 
-use super::Result;
+use super::{AOCError, Params, Result};
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
-    let grid = Grid::parse(data)?;
-    //let res1 = solve_iter(&grid);
-    let res1 = solve(&grid);
-    println!("res1: {}", res1);
-    Ok(())
-}
+const DIRECTIONS: [Point; 4] = [
+    Point { x: -1, y: 0 },
+    Point { x: 1, y: 0 },
+    Point { x: 0, y: -1 },
+    Point { x: 0, y: 1 },
+];
 
-fn _solve_iter(grid: &Grid) -> usize {
-    let worst_cheat = 20;
-    let mut cheats: HashSet<Point> = HashSet::new();
-    let baseline = solve_nocheat(grid);
-    for wall in grid.walls.iter() {
-        if let Some(new_grid) = _cheated_grid(grid, wall) {
-            if solve_nocheat(&new_grid) + worst_cheat <= baseline {
-                cheats.insert(*wall);
-            }
-        }
+pub const PARAMS: &[&str] = &["threshold", "radius", "histogram"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    let grid = Grid::parse(data)?;
+    let threshold = params.get_or("threshold", 100)?;
+    let radius = params.get_or("radius", 20)?;
+    let show_histogram = params.flag("histogram")?;
+    let race = Race::new(&grid)?;
+    if verbosity > 1 {
+        println!("no cheat length: {}", race.baseline);
     }
-    cheats.len()
+    let res1 = race.cheats(&grid, 2, threshold);
+    let res2 = race.cheats(&grid, radius, threshold);
+    if show_histogram {
+        print_histogram("part1 (radius 2)", &res1);
+        print_histogram(&format!("part2 (radius {radius})"), &res2);
+    }
+    println!("res1: {}", res1.values().sum::<usize>());
+    println!("res2: {}", res2.values().sum::<usize>());
+    Ok(())
 }
 
-fn _cheated_grid(grid: &Grid, wall: &Point) -> Option<Grid> {
-    let mut is_viable = false;
-    for delta in [
-        Point { x: -1, y: 0 },
-        Point { x: 1, y: 0 },
-        Point { x: 0, y: -1 },
-        Point { x: 0, y: 1 },
-    ] {
-        let next_p = *wall + delta;
-        if next_p.x < 0
-            || next_p.x > grid.x_bounds
-            || next_p.y < 0
-            || next_p.y > grid.y_bounds
-            || grid.walls.contains(&next_p)
-        {
-            continue;
-        }
-        if is_viable {
-            let mut new_walls = grid.walls.clone();
-            new_walls.remove(wall);
-            return Some(Grid {
-                walls: new_walls,
-                start: grid.start,
-                end: grid.end,
-                x_bounds: grid.x_bounds,
-                y_bounds: grid.y_bounds,
-            });
-        } else {
-            is_viable = true;
-        }
+fn print_histogram(title: &str, savings: &BTreeMap<i32, usize>) {
+    println!("{title}:");
+    for (saved, count) in savings {
+        println!("{count} cheats save {saved} picoseconds");
     }
-    None
+    println!();
 }
 
-fn solve(grid: &Grid) -> usize {
-    let no_cheat_length = solve_nocheat(grid);
-    println!("n: {}", no_cheat_length);
-    let mut queue: BinaryHeap<State> = BinaryHeap::new();
-    queue.push(State {
-        g_cost: 0,
-        cheat: Cheat::default(),
-        current: grid.start,
-        h_cost: grid.get_cost(&grid.start),
-        cheat_time: 0,
-    });
-    let mut visited: HashMap<Point, i32> = HashMap::new();
-    let mut cheats: HashSet<Cheat> = HashSet::new();
-    let mut cheated_states: HashSet<State> = HashSet::new();
-    let best_cheat_times = 64;
-    while let Some(next) = queue.pop() {
-        if next.current == grid.end {
-            println!("{}", cheats.len());
-            if next.g_cost + best_cheat_times > no_cheat_length {
-                break;
-            }
-            cheats.insert(next.cheat.clone());
-            cheated_states.insert(next);
-            //println!("{:#?}", cheats);
-
-            continue;
-        }
-        if let Some(old) = visited.get(&next.current) {
-            if *old + best_cheat_times < next.g_cost {
-                continue;
-            }
-        }
-        if cheats.contains(&next.cheat) {
-            continue;
-        }
-        visited.insert(next.current, next.g_cost);
-        for s in next_p(&next, grid, true) {
-            queue.push(s);
-        }
-    }
-    //println!("{:#?}", cheats);
-    //println!("{:#?}", cheated_states);
-    cheats.len()
+/// Distances along the track from start and from end.
+struct Race {
+    from_start: Vec<Option<i32>>,
+    from_end: Vec<Option<i32>>,
+    baseline: i32,
 }
 
-fn solve_nocheat(grid: &Grid) -> i32 {
-    let mut queue: BinaryHeap<State> = BinaryHeap::new();
-    queue.push(State {
-        g_cost: 0,
-        cheat: Cheat::default(),
-        current: grid.start,
-        h_cost: grid.get_cost(&grid.start),
-        cheat_time: 0,
-    });
-    let mut visited: HashMap<Point, i32> = HashMap::new();
-    while let Some(next) = queue.pop() {
-        if next.current == grid.end {
-            return next.g_cost;
-        }
-        if let Some(old) = visited.get(&next.current) {
-            if *old <= next.g_cost {
-                continue;
-            }
-        }
-        visited.insert(next.current, next.g_cost);
-        for s in next_p(&next, grid, false) {
-            queue.push(s);
-        }
+impl Race {
+    fn new(grid: &Grid) -> Result<Self> {
+        let from_start = grid.distances(grid.start);
+        let from_end = grid.distances(grid.end);
+        let baseline = grid
+            .idx(&grid.end)
+            .and_then(|idx| from_start[idx])
+            .ok_or(AOCError::SolverError("end is not reachable".into()))?;
+        Ok(Self {
+            from_start,
+            from_end,
+            baseline,
+        })
     }
-    0
-}
 
-fn next_p(current: &State, grid: &Grid, cheat: bool) -> Vec<State> {
-    let mut next_states = Vec::new();
-    for delta in [
-        Point { x: -1, y: 0 },
-        Point { x: 1, y: 0 },
-        Point { x: 0, y: -1 },
-        Point { x: 0, y: 1 },
-    ] {
-        let next_point = current.current + delta;
-        let is_wall = grid.walls.contains(&next_point);
-        if is_wall && cheat {
-            if current.cheat_time >= 19 {
-                continue;
-            }
-            if current.cheat_time == 0 {
-                let cheat = Cheat {
-                    start: Some(next_point),
-                    end: None,
+    /// Counts cheats of at most `radius` picoseconds saving at least `threshold`, keyed by savings.
+    fn cheats(&self, grid: &Grid, radius: i32, threshold: i32) -> BTreeMap<i32, usize> {
+        let mut savings = BTreeMap::new();
+        for y in 0..=grid.y_bounds {
+            for x in 0..=grid.x_bounds {
+                let start = Point { x, y };
+                let Some(to_start) = grid.idx(&start).and_then(|idx| self.from_start[idx]) else {
+                    continue;
                 };
-                next_states.push(State {
-                    h_cost: grid.get_cost(&next_point),
-                    g_cost: current.g_cost + 1,
-                    cheat,
-                    current: next_point,
-                    cheat_time: 1,
-                });
-            } else {
-                let cheat = if current.cheat.end.is_none() {
-                    current.cheat.clone()
-                } else {
-                    Cheat {
-                        start: current.cheat.start,
-                        end: None,
+                for dy in -radius..=radius {
+                    let rem = radius - dy.abs();
+                    for dx in -rem..=rem {
+                        let end = start + Point { x: dx, y: dy };
+                        let Some(to_end) = grid.idx(&end).and_then(|idx| self.from_end[idx]) else {
+                            continue;
+                        };
+                        let saved = self.baseline - (to_start + dx.abs() + dy.abs() + to_end);
+                        if saved >= threshold {
+                            *savings.entry(saved).or_default() += 1;
+                        }
                     }
-                };
-                next_states.push(State {
-                    h_cost: grid.get_cost(&next_point),
-                    g_cost: current.g_cost + 1,
-                    cheat,
-                    current: next_point,
-                    cheat_time: current.cheat_time + 1,
-                });
-            }
-        } else if !is_wall {
-            let next_cheat = if current.cheat.start.is_some() && current.cheat.end.is_none() {
-                Cheat {
-                    start: current.cheat.start,
-                    end: Some(next_point),
                 }
-            } else {
-                current.cheat.clone()
-            };
-            next_states.push(State {
-                cheat_time: if current.cheat_time == 20 || current.cheat_time == 0 {
-                    current.cheat_time
-                } else {
-                    current.cheat_time + 1
-                },
-                g_cost: current.g_cost + 1,
-                h_cost: grid.get_cost(&next_point),
-                cheat: next_cheat,
-                current: next_point,
-            });
+            }
         }
+        savings
     }
-    next_states
 }
 
 #[derive(Hash, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -229,33 +120,6 @@ impl Add for Point {
     }
 }
 
-#[derive(Default, Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Clone)]
-struct Cheat {
-    start: Option<Point>,
-    end: Option<Point>,
-}
-
-#[derive(Default, Debug, PartialEq, Eq, Hash)]
-struct State {
-    h_cost: i32,
-    g_cost: i32,
-    cheat: Cheat,
-    current: Point,
-    cheat_time: u8,
-}
-
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        (other.g_cost + other.h_cost).cmp(&(self.g_cost + self.h_cost))
-    }
-}
-
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 #[derive(Default, Debug)]
 struct Grid {
     walls: HashSet<Point>,
@@ -267,7 +131,10 @@ struct Grid {
 
 impl Grid {
     fn parse(data: PathBuf) -> Result<Self> {
-        let f = fs::read_to_string(data)?;
+        Self::parse_str(&fs::read_to_string(data)?)
+    }
+
+    fn parse_str(f: &str) -> Result<Self> {
         let f = f.lines();
         let mut grid = Grid::default();
         for (y, line) in f.enumerate() {
@@ -301,7 +168,71 @@ impl Grid {
         Ok(grid)
     }
 
-    fn get_cost(&self, point: &Point) -> i32 {
-        (self.end.x.abs_diff(point.x) + self.end.y.abs_diff(point.y)) as i32
+    fn idx(&self, point: &Point) -> Option<usize> {
+        if point.x < 0 || point.x > self.x_bounds || point.y < 0 || point.y > self.y_bounds {
+            return None;
+        }
+        Some((point.y * (self.x_bounds + 1) + point.x) as usize)
+    }
+
+    /// BFS distances from `from` to every reachable track cell.
+    fn distances(&self, from: Point) -> Vec<Option<i32>> {
+        let mut dist = vec![None; ((self.x_bounds + 1) * (self.y_bounds + 1)) as usize];
+        let mut queue = VecDeque::from([from]);
+        if let Some(idx) = self.idx(&from) {
+            dist[idx] = Some(0);
+        }
+        while let Some(current) = queue.pop_front() {
+            let d = dist[self.idx(&current).expect("queued points are in bounds")]
+                .expect("queued points have a distance");
+            for delta in DIRECTIONS {
+                let next = current + delta;
+                if self.walls.contains(&next) {
+                    continue;
+                }
+                if let Some(idx) = self.idx(&next)
+                    && dist[idx].is_none()
+                {
+                    dist[idx] = Some(d + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        dist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############
+";
+
+    #[test]
+    fn example() {
+        let grid = Grid::parse_str(EXAMPLE).unwrap();
+        let race = Race::new(&grid).unwrap();
+        assert_eq!(race.baseline, 84);
+        let short = race.cheats(&grid, 2, 64);
+        assert_eq!(short, BTreeMap::from([(64, 1)]));
+        let long = race.cheats(&grid, 20, 50);
+        assert_eq!(long.values().sum::<usize>(), 285);
+        assert_eq!(long.get(&76), Some(&3));
     }
 }
@@ -1,81 +1,104 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{fs, path::PathBuf, thread};
 
 use super::{AOCError, Result};
 
+/// Number of distinct windows of four price changes, each in -9..=9.
+const WINDOWS: usize = 19 * 19 * 19 * 19;
+const ITERATIONS: usize = 2000;
+
 pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
     let nums = parse(data)?;
-    let res1 = solve(&nums);
-    let res2 = best_sequence(&nums);
-    println!("res1: {}, res2: {}", res1, res2);
+    let market = simulate(&nums);
+    let (sequence, bananas) = market.best_sequence();
+    println!("res1: {}, res2: {}", market.secret_sum, bananas);
+    println!("best sequence: {:?}", sequence);
     Ok(())
 }
 
-fn best_sequence(nums: &Vec<u64>) -> i64 {
-    let mut all_changes: HashMap<[i8; 4], i64> = HashMap::new();
-    let mut prices = HashMap::new();
-    for num in nums {
-        let mut last_changes = [0, 0, 0, 0];
-        let mut number = first_four(num, &mut last_changes, &mut prices);
-        for _ in 4..2000 {
-            number = next_change(&number, &mut last_changes, &mut prices);
-        }
-        for (k, v) in prices.drain() {
-            all_changes
-                .entry(k)
-                .and_modify(|e| *e += v as i64)
-                .or_insert(v as i64);
-        }
-    }
-    let mut max = i64::MIN;
-    for v in all_changes.values() {
-        max = max.max(*v);
-    }
-    max
+/// Combined results over all buyers.
+struct Market {
+    secret_sum: u64,
+    /// bananas per window index, see `window_index`
+    bananas: Vec<u32>,
 }
 
-fn first_four(num: &u64, last_changes: &mut [i8; 4], prices: &mut HashMap<[i8; 4], i8>) -> u64 {
-    let mut next_num = next_secret_num(num);
-    let last_digit = (next_num % 10) as i8;
-    let prev_last_digit = (*num % 10) as i8;
-    *last_changes.first_mut().unwrap() = last_digit - prev_last_digit;
-    let mut prev_num = last_digit;
-    for i in 1..4 {
-        next_num = next_secret_num(&next_num);
-        let last_digit = (next_num % 10) as i8;
-        let prev_last_digit = prev_num;
-        prev_num = last_digit;
-        *last_changes.get_mut(i).unwrap() = last_digit - prev_last_digit;
+impl Market {
+    fn best_sequence(&self) -> ([i8; 4], u32) {
+        let (idx, bananas) = self
+            .bananas
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, b)| **b)
+            .map_or((0, 0), |(idx, b)| (idx, *b));
+        (window_changes(idx), bananas)
     }
-    prices.insert(*last_changes, last_digit);
-    next_num
 }
 
-fn next_change(num: &u64, last_changes: &mut [i8; 4], prices: &mut HashMap<[i8; 4], i8>) -> u64 {
-    let next_num = next_secret_num(num);
-    last_changes.rotate_left(1);
-    let last_digit = (next_num % 10) as i8;
-    let prev_last_digit = (*num % 10) as i8;
-    *last_changes.last_mut().unwrap() = last_digit - prev_last_digit;
-    if !prices.contains_key(last_changes) {
-        prices.insert(*last_changes, last_digit);
-    }
-    next_num
+/// Interprets the last four changes as base 19 digits.
+fn window_index(idx: usize, change: i8) -> usize {
+    (idx * 19 + (change + 9) as usize) % WINDOWS
 }
 
-fn solve(nums: &Vec<u64>) -> u64 {
-    let mut tot = 0;
-    for n in nums {
-        tot += nth_secret_num(n, 2000);
+fn window_changes(mut idx: usize) -> [i8; 4] {
+    let mut changes = [0; 4];
+    for change in changes.iter_mut().rev() {
+        *change = (idx % 19) as i8 - 9;
+        idx /= 19;
     }
-    tot
+    changes
 }
 
-fn nth_secret_num(num: &u64, n: usize) -> u64 {
-    let mut number = next_secret_num(num);
-    for _ in 1..n {
-        number = next_secret_num(&number);
+/// Splits the buyers across threads, each summing into its own accumulator.
+fn simulate(nums: &[u64]) -> Market {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = nums.len().div_ceil(threads).max(1);
+    thread::scope(|s| {
+        nums.chunks(chunk_size)
+            .map(|chunk| s.spawn(|| simulate_buyers(chunk)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().expect("buyer simulation panicked"))
+            .fold(
+                Market {
+                    secret_sum: 0,
+                    bananas: vec![0; WINDOWS],
+                },
+                |mut acc, market| {
+                    acc.secret_sum += market.secret_sum;
+                    for (total, b) in acc.bananas.iter_mut().zip(market.bananas) {
+                        *total += b;
+                    }
+                    acc
+                },
+            )
+    })
+}
+
+fn simulate_buyers(nums: &[u64]) -> Market {
+    let mut market = Market {
+        secret_sum: 0,
+        bananas: vec![0; WINDOWS],
+    };
+    // buyer (+1) that last sold at a window, a buyer only sells at the first occurrence
+    let mut last_seen = vec![0_u32; WINDOWS];
+    for (buyer, num) in nums.iter().enumerate() {
+        let buyer = buyer as u32 + 1;
+        let mut number = *num;
+        let mut price = (number % 10) as i8;
+        let mut idx = 0;
+        for i in 0..ITERATIONS {
+            number = next_secret_num(&number);
+            let next_price = (number % 10) as i8;
+            idx = window_index(idx, next_price - price);
+            price = next_price;
+            if i >= 3 && last_seen[idx] != buyer {
+                last_seen[idx] = buyer;
+                market.bananas[idx] += price as u32;
+            }
+        }
+        market.secret_sum += number;
     }
-    number
+    market
 }
 
 fn next_secret_num(num: &u64) -> u64 {
@@ -95,3 +118,17 @@ fn parse(data: PathBuf) -> Result<Vec<u64>> {
         })
         .collect::<Result<Vec<u64>>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        assert_eq!(simulate(&[1, 10, 100, 2024]).secret_sum, 37327623);
+        assert_eq!(
+            simulate(&[1, 2, 3, 2024]).best_sequence(),
+            ([-2, 1, -1, 3], 23)
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+/// Fixed size set of node ids backed by u64 words.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(size: usize) -> Self {
+        Self {
+            words: vec![0; size.div_ceil(64)],
+        }
+    }
+
+    pub fn full(size: usize) -> Self {
+        let mut set = Self::new(size);
+        for id in 0..size {
+            set.insert(id);
+        }
+        set
+    }
+
+    fn grow(&mut self, size: usize) {
+        let len = size.div_ceil(64);
+        if self.words.len() < len {
+            self.words.resize(len, 0);
+        }
+    }
+
+    pub fn insert(&mut self, id: usize) {
+        self.grow(id + 1);
+        self.words[id / 64] |= 1 << (id % 64);
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        if let Some(word) = self.words.get_mut(id / 64) {
+            *word &= !(1 << (id % 64));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            words: self
+                .words
+                .iter()
+                .zip(other.words.iter())
+                .map(|(a, b)| a & b)
+                .collect(),
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let (long, short) = if self.words.len() >= other.words.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut words = long.words.clone();
+        for (w, o) in words.iter_mut().zip(short.words.iter()) {
+            *w |= o;
+        }
+        Self { words }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            words: self
+                .words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| w & !other.words.get(i).copied().unwrap_or(0))
+                .collect(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Undirected graph over interned node names.
+#[derive(Default, Debug)]
+pub struct Graph {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    adj: Vec<BitSet>,
+}
+
+impl Graph {
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len();
+        self.names.push(name.into());
+        self.ids.insert(name.into(), id);
+        self.adj.push(BitSet::new(0));
+        id
+    }
+
+    pub fn add_edge(&mut self, a: &str, b: &str) {
+        let a = self.intern(a);
+        let b = self.intern(b);
+        self.adj[a].insert(b);
+        self.adj[b].insert(a);
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    /// All triangles as id triples in ascending order, each reported once.
+    pub fn triangles(&self) -> Vec<[usize; 3]> {
+        let mut triangles = Vec::new();
+        for (a, neighbours) in self.adj.iter().enumerate() {
+            for b in neighbours.iter().filter(|b| *b > a) {
+                let common = neighbours.intersection(&self.adj[b]);
+                triangles.extend(common.iter().filter(|c| *c > b).map(|c| [a, b, c]));
+            }
+        }
+        triangles
+    }
+
+    /// Maximum clique via Bron–Kerbosch with pivoting.
+    pub fn max_clique(&self) -> Vec<usize> {
+        let mut best = Vec::new();
+        let mut current = Vec::new();
+        self.bron_kerbosch(
+            &mut current,
+            BitSet::full(self.len()),
+            BitSet::new(self.len()),
+            &mut best,
+        );
+        best.sort();
+        best
+    }
+
+    fn bron_kerbosch(
+        &self,
+        current: &mut Vec<usize>,
+        mut candidates: BitSet,
+        mut excluded: BitSet,
+        best: &mut Vec<usize>,
+    ) {
+        if candidates.is_empty() {
+            if excluded.is_empty() && current.len() > best.len() {
+                *best = current.clone();
+            }
+            return;
+        }
+        if current.len() + candidates.len() <= best.len() {
+            return;
+        }
+        // pivot on the vertex covering the most candidates to minimize branching
+        let pivot = candidates
+            .union(&excluded)
+            .iter()
+            .max_by_key(|p| candidates.intersection(&self.adj[*p]).len())
+            .expect("candidates is not empty");
+        for v in candidates.difference(&self.adj[pivot]).iter() {
+            current.push(v);
+            self.bron_kerbosch(
+                current,
+                candidates.intersection(&self.adj[v]),
+                excluded.intersection(&self.adj[v]),
+                best,
+            );
+            current.pop();
+            candidates.remove(v);
+            excluded.insert(v);
+        }
+    }
+}
@@ -1,57 +1,248 @@
 use std::{fs, path::PathBuf};
 
-use super::Result;
+use super::{AOCError, Params, Result};
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
-    let (keys, locks) = parse(data)?;
-    let res1 = matching_keylocks(&keys, &locks);
+pub const PARAMS: &[&str] = &["pairs"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    let schematics = Schematics::parse_str(&fs::read_to_string(data)?)?;
+    if verbosity > 1 {
+        println!(
+            "{} locks, {} keys, {}x{} schematics",
+            schematics.locks.len(),
+            schematics.keys.len(),
+            schematics.width,
+            schematics.height
+        );
+    }
+    if params.flag("pairs")? {
+        schematics.print_pairs();
+    }
+    let res1 = FitIndex::new(&schematics).count_fits(&schematics);
     println!("res1: {}", res1);
     Ok(())
 }
 
-fn matching_keylocks(keys: &[KeyLock], locks: &[KeyLock]) -> usize {
-    let mut tot = 0;
-    for k in keys.iter() {
-        for l in locks.iter() {
-            if is_matching(k, l) {
-                tot += 1;
+/// Pin heights per column, not counting the solid base row.
+type KeyLock = Vec<usize>;
+
+#[derive(Debug, Default)]
+struct Schematics {
+    width: usize,
+    height: usize,
+    locks: Vec<KeyLock>,
+    keys: Vec<KeyLock>,
+}
+
+impl Schematics {
+    fn parse_str(input: &str) -> Result<Self> {
+        let lines = input.lines().collect::<Vec<&str>>();
+        let mut schematics = Schematics::default();
+        for (n, block) in lines
+            .split(|line| line.is_empty())
+            .filter(|block| !block.is_empty())
+            .enumerate()
+        {
+            let invalid = |msg: &str| AOCError::ParseError(format!("schematic {n}: {msg}"));
+            let width = block[0].len();
+            if n == 0 {
+                schematics.width = width;
+                schematics.height = block.len();
+            }
+            if block.len() != schematics.height || block.iter().any(|l| l.len() != schematics.width)
+            {
+                return Err(invalid(&format!(
+                    "expected {}x{} block",
+                    schematics.width, schematics.height
+                )));
+            }
+            if schematics.height < 2 || width == 0 {
+                return Err(invalid("schematic needs at least two rows and one column"));
+            }
+            let is_lock = block[0].bytes().all(|c| c == b'#');
+            let is_key = block[0].bytes().all(|c| c == b'.');
+            if !is_lock && !is_key {
+                return Err(invalid("top row must be all '#' (lock) or all '.' (key)"));
+            }
+            // Reading a key bottom up makes it look like a lock.
+            let rows: Vec<&[u8]> = if is_lock {
+                block.iter().map(|l| l.as_bytes()).collect()
+            } else {
+                block.iter().rev().map(|l| l.as_bytes()).collect()
+            };
+            let mut keylock = Vec::with_capacity(width);
+            for col in 0..width {
+                let filled = rows.iter().take_while(|row| row[col] == b'#').count();
+                if filled == 0 {
+                    return Err(invalid(&format!("column {col} is missing its base")));
+                }
+                if filled == rows.len() {
+                    return Err(invalid(&format!("column {col} has no free space")));
+                }
+                if rows[filled..].iter().any(|row| row[col] != b'.') {
+                    return Err(invalid(&format!("column {col} is not a contiguous pin")));
+                }
+                keylock.push(filled - 1);
+            }
+            if is_lock {
+                schematics.locks.push(keylock);
+            } else {
+                schematics.keys.push(keylock);
             }
         }
+        if schematics.height == 0 {
+            return Err(AOCError::ParseError("no schematics in the input".into()));
+        }
+        Ok(schematics)
     }
-    tot
-}
 
-fn is_matching(key: &KeyLock, lock: &KeyLock) -> bool {
-    for (k, l) in key.iter().zip(lock.iter()) {
-        if k + l > 5 {
-            return false;
+    /// Room for a key and lock pin to share in one column.
+    fn space(&self) -> usize {
+        self.height - 2
+    }
+
+    /// First column where key and lock overlap, `None` when they fit.
+    fn blocking_column(&self, lock: &KeyLock, key: &KeyLock) -> Option<usize> {
+        lock.iter().zip(key).position(|(l, k)| l + k > self.space())
+    }
+
+    fn print_pairs(&self) {
+        for (i, lock) in self.locks.iter().enumerate() {
+            for (j, key) in self.keys.iter().enumerate() {
+                match self.blocking_column(lock, key) {
+                    None => println!("lock {i}, key {j}: fits"),
+                    Some(col) => println!("lock {i}, key {j}: overlap in column {col}"),
+                }
+            }
         }
     }
-    true
 }
 
-type KeyLock = [u8; 5];
-
-fn parse(data: PathBuf) -> Result<(Vec<KeyLock>, Vec<KeyLock>)> {
-    let f = fs::read_to_string(data)?;
-    let f = f.lines().collect::<Vec<&str>>();
-    let mut locks = Vec::new();
-    let mut keys = Vec::new();
-    for block in f.split(|line| line.is_empty()) {
-        let mut keylock = [0, 0, 0, 0, 0];
-        for line in block.iter() {
-            for (i, c) in line.chars().enumerate() {
-                if c == '#' {
-                    keylock[i] += 1;
+/// For every column and height, the set of keys whose pin is at most that high.
+struct FitIndex {
+    at_most: Vec<Vec<Vec<u64>>>,
+}
+
+impl FitIndex {
+    fn new(schematics: &Schematics) -> Self {
+        let words = schematics.keys.len().div_ceil(64);
+        let heights = schematics.space() + 1;
+        let mut at_most = vec![vec![vec![0u64; words]; heights]; schematics.width];
+        for (k, key) in schematics.keys.iter().enumerate() {
+            for (col, &pin) in key.iter().enumerate() {
+                for bucket in at_most[col].iter_mut().skip(pin) {
+                    bucket[k / 64] |= 1 << (k % 64);
                 }
             }
         }
-        keylock.iter_mut().for_each(|item| *item -= 1);
-        if block[0].contains('.') {
-            locks.push(keylock);
-        } else {
-            keys.push(keylock);
+        Self { at_most }
+    }
+
+    /// Keys fitting `lock`, as the intersection of the per column buckets.
+    fn fits(&self, schematics: &Schematics, lock: &KeyLock) -> usize {
+        let Some(mut fits) = self.at_most.first().map(|col| vec![u64::MAX; col[0].len()]) else {
+            return 0;
+        };
+        for (col, &pin) in lock.iter().enumerate() {
+            let Some(room) = schematics.space().checked_sub(pin) else {
+                return 0;
+            };
+            for (f, b) in fits.iter_mut().zip(&self.at_most[col][room]) {
+                *f &= b;
+            }
         }
+        fits.iter().map(|f| f.count_ones() as usize).sum()
+    }
+
+    fn count_fits(&self, schematics: &Schematics) -> usize {
+        schematics
+            .locks
+            .iter()
+            .map(|lock| self.fits(schematics, lock))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+#####
+.####
+.####
+.####
+.#.#.
+.#...
+.....
+
+#####
+##.##
+.#.##
+...##
+...#.
+...#.
+.....
+
+.....
+#....
+#....
+#...#
+#.#.#
+#.###
+#####
+
+.....
+.....
+#.#..
+###..
+###.#
+###.#
+#####
+
+.....
+.....
+.....
+#....
+#.#..
+#.#.#
+#####
+";
+
+    #[test]
+    fn example() {
+        let schematics = Schematics::parse_str(EXAMPLE).unwrap();
+        assert_eq!(
+            schematics.locks,
+            vec![vec![0, 5, 3, 4, 3], vec![1, 2, 0, 5, 3]]
+        );
+        assert_eq!(schematics.keys[0], vec![5, 0, 2, 1, 3]);
+        assert_eq!(FitIndex::new(&schematics).count_fits(&schematics), 3);
+        assert_eq!(
+            schematics.blocking_column(&schematics.locks[0], &schematics.keys[0]),
+            Some(4)
+        );
+        assert_eq!(
+            schematics.blocking_column(&schematics.locks[0], &schematics.keys[2]),
+            None
+        );
+    }
+
+    #[test]
+    fn other_sizes() {
+        let input = "###\n#.#\n...\n\n...\n..#\n###\n\n...\n.#.\n###\n";
+        let schematics = Schematics::parse_str(input).unwrap();
+        assert_eq!((schematics.width, schematics.height), (3, 3));
+        assert_eq!(FitIndex::new(&schematics).count_fits(&schematics), 1);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Schematics::parse_str("###\n.#.\n#..\n").is_err());
+        assert!(Schematics::parse_str("#.#\n...\n...\n").is_err());
+        assert!(Schematics::parse_str("###\n...\n\n###\n...\n...\n").is_err());
+        assert!(Schematics::parse_str("...\n.#.\n##.\n").is_err());
+        assert!(Schematics::parse_str("").is_err());
+        assert!(Schematics::parse_str("\n\n").is_err());
     }
-    Ok((keys, locks))
 }
@@ -1,62 +1,188 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
+use std::fmt::Display;
 use std::fs;
 use std::path::PathBuf;
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
-    let res = parse_input(&data, false)?;
-    let res2 = parse_input(&data, true)?;
+pub const PARAMS: &[&str] = &["tokens"];
+
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
+    let f = fs::read(data)?;
+    let tokens = lex(&f)?;
+    if params.flag("tokens")? {
+        for token in &tokens {
+            println!("{:>6}: {}", token.offset, token.instruction);
+        }
+    }
+    let res = run(&tokens, false);
+    let res2 = run(&tokens, true);
     println!("res1: {res}, res2: {res2}");
     Ok(())
 }
 
-fn parse_input(data: &PathBuf, do_enabled: bool) -> Result<u64> {
-    let f = fs::read(data)?;
-    let pattern = b"mul(";
-    let do_pattern = b"do";
-    let dont_pattern = b"don't";
-    let mut tot = 0;
-    let mut last_do = true;
-    for (i, w) in f.windows(5).enumerate() {
-        if &w[..2] == do_pattern {
-            last_do = w != dont_pattern;
-        }
-        if !last_do && do_enabled {
-            continue;
-        }
-        if &w[..4] == pattern {
-            let remainder = &f[i + 4..i + 12];
-            let mut iter = remainder.split(|item| *item == b',');
-            if let (Some(first), Some(second)) = (iter.next(), iter.next()) {
-                let (v1, v2);
-                match parse_to_num(first) {
-                    Ok(val) => v1 = val,
-                    Err(AOCError::ParseError(_e)) => continue,
-                    Err(e) => return Err(e),
-                }
-                match parse_to_num(second.split(|num| *num == b')').next().expect("cant err")) {
-                    Ok(val) => v2 = val,
-                    Err(AOCError::ParseError(_e)) => continue,
-                    Err(e) => return Err(e),
-                }
-                tot += mul(v1, v2);
+/// Longest number literal accepted as an argument.
+const MAX_DIGITS: usize = 3;
+
+/// Name and argument count of every instruction the lexer recognizes.
+///
+/// A new instruction needs an entry here, a variant in [`Instruction`] and an arm in
+/// [`Machine::execute`].
+const SPECS: [(&str, usize); 3] = [("mul", 2), ("do", 0), ("don't", 0)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    Mul(u64, u64),
+    Do,
+    Dont,
+}
+
+impl Instruction {
+    fn build(name: &str, args: &[u64]) -> Result<Self> {
+        match (name, args) {
+            ("mul", [a, b]) => Ok(Instruction::Mul(*a, *b)),
+            ("do", []) => Ok(Instruction::Do),
+            ("don't", []) => Ok(Instruction::Dont),
+            _ => Err(AOCError::ParseError(format!(
+                "no instruction {name} with {} arguments",
+                args.len()
+            ))),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Mul(a, b) => write!(f, "mul({a},{b})"),
+            Instruction::Do => write!(f, "do()"),
+            Instruction::Dont => write!(f, "don't()"),
+        }
+    }
+}
+
+/// A well-formed instruction and the byte offset where it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token {
+    offset: usize,
+    instruction: Instruction,
+}
+
+/// Scans the input for well-formed instructions, everything else is skipped.
+fn lex(input: &[u8]) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    while offset < input.len() {
+        let rest = &input[offset..];
+        let matched = SPECS
+            .iter()
+            .find_map(|(name, arity)| match_call(rest, name, *arity).map(|m| (name, m)));
+        match matched {
+            Some((name, (args, len))) => {
+                tokens.push(Token {
+                    offset,
+                    instruction: Instruction::build(name, &args)?,
+                });
+                offset += len;
+            }
+            None => offset += 1,
+        }
+    }
+    Ok(tokens)
+}
+
+/// Matches `name(arg,...)` with exactly `arity` arguments at the start of `input`, returning
+/// the arguments and the length of the call.
+fn match_call(input: &[u8], name: &str, arity: usize) -> Option<(Vec<u64>, usize)> {
+    let mut pos = name.len();
+    if !input.starts_with(name.as_bytes()) || input.get(pos) != Some(&b'(') {
+        return None;
+    }
+    pos += 1;
+    let mut args = Vec::with_capacity(arity);
+    for i in 0..arity {
+        if i > 0 {
+            if input.get(pos) != Some(&b',') {
+                return None;
             }
+            pos += 1;
         }
+        let digits = input[pos..]
+            .iter()
+            .take(MAX_DIGITS + 1)
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 || digits > MAX_DIGITS {
+            return None;
+        }
+        args.push(parse_to_num(&input[pos..pos + digits]));
+        pos += digits;
+    }
+    if input.get(pos) != Some(&b')') {
+        return None;
     }
+    Some((args, pos + 1))
+}
+
+fn parse_to_num(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |res, b| 10 * res + (*b - b'0') as u64)
+}
 
-    Ok(tot)
+#[derive(Debug)]
+struct Machine {
+    enabled: bool,
+    total: u64,
 }
 
-fn mul(n1: u64, n2: u64) -> u64 {
-    n1 * n2
+impl Machine {
+    fn execute(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Mul(a, b) if self.enabled => self.total += a * b,
+            Instruction::Mul(..) => {}
+            Instruction::Do => self.enabled = true,
+            Instruction::Dont => self.enabled = false,
+        }
+    }
 }
 
-fn parse_to_num(bytes: &[u8]) -> Result<u64> {
-    let mut res = 0;
-    for b in bytes {
-        if !b.is_ascii_digit() {
-            return Err(AOCError::ParseError("NaN".into()));
+/// Sums all products, only honouring `do()`/`don't()` when `conditionals` is set.
+fn run(tokens: &[Token], conditionals: bool) -> u64 {
+    let mut machine = Machine {
+        enabled: true,
+        total: 0,
+    };
+    for token in tokens {
+        match token.instruction {
+            Instruction::Do | Instruction::Dont if !conditionals => {}
+            instruction => machine.execute(&instruction),
         }
-        res = 10 * res + *b as u64 - 48;
     }
-    Ok(res)
+    machine.total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let input = b"xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+        let tokens = lex(input).unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0].offset, 1);
+        assert_eq!(run(&tokens, false), 161);
+    }
+
+    #[test]
+    fn conditionals() {
+        let input = b"xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let tokens = lex(input).unwrap();
+        assert_eq!(tokens[1].instruction, Instruction::Dont);
+        assert_eq!(run(&tokens, false), 161);
+        assert_eq!(run(&tokens, true), 48);
+    }
+
+    #[test]
+    fn malformed() {
+        let tokens = lex(b"mul(1234,5)mul(1,2 mul(,3)do(1)mul(4,5").unwrap();
+        assert!(tokens.is_empty());
+    }
 }
@@ -1,14 +1,56 @@
-use super::Result;
+use super::{AOCError, Params, Result};
+use std::collections::HashSet;
+use std::fmt::Display;
 use std::fs;
 use std::path::PathBuf;
 
 type Array = Vec<Vec<u8>>;
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
+/// Row and column steps of the 8 search directions, with their compass names.
+const DIRECTIONS: [(i64, i64, &str); 8] = [
+    (0, 1, "E"),
+    (1, 1, "SE"),
+    (1, 0, "S"),
+    (1, -1, "SW"),
+    (0, -1, "W"),
+    (-1, -1, "NW"),
+    (-1, 0, "N"),
+    (-1, 1, "NE"),
+];
+
+pub const PARAMS: &[&str] = &["word", "stencil", "matches", "highlight"];
+
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
     let arr = parse_data(data)?;
-    let res = search(&arr);
-    let res2 = search_x(&arr);
-    println!("part1: {}, part2: {}", res, res2);
+    let word = params.get_or("word", "XMAS".to_string())?;
+    let stencil = Stencil::parse(&params.get_or("stencil", "M.S/.A./M.S".to_string())?)?;
+    let show_matches = params.flag("matches")?;
+    let highlight = params.flag("highlight")?;
+    let words = find_word(&arr, word.as_bytes());
+    let shapes = find_stencil(&arr, &stencil);
+    if show_matches {
+        for m in &words {
+            println!(
+                "{word} at ({}, {}) going {}",
+                m.row, m.col, DIRECTIONS[m.direction].2
+            );
+        }
+        for m in &shapes {
+            println!(
+                "stencil at ({}, {}) rotated {} degrees",
+                m.row,
+                m.col,
+                m.rotation * 90
+            );
+        }
+    }
+    if highlight {
+        let cells = words.iter().flat_map(|m| m.cells(word.len())).collect();
+        println!("{}\n", Highlight(&arr, &cells));
+        let cells = shapes.iter().flat_map(|m| m.cells(&stencil)).collect();
+        println!("{}\n", Highlight(&arr, &cells));
+    }
+    println!("part1: {}, part2: {}", words.len(), shapes.len());
     Ok(())
 }
 
@@ -21,124 +63,263 @@ fn parse_data(data: PathBuf) -> Result<Array> {
     Ok(d.collect::<Array>())
 }
 
-fn get_diags(arr: &Array) -> Array {
-    let mut res = Array::new();
-    for k in 0..arr[0].len() + arr.len() - 1 {
-        res.push(Vec::new());
-        for r in 0..arr.len() {
-            for c in 0..arr[0].len() {
-                if r as i64 == c as i64 + k as i64 - arr[0].len() as i64 + 1 {
-                    res.last_mut().unwrap().push(arr[r][c]);
-                }
-            }
-        }
+fn get(arr: &Array, row: i64, col: i64) -> Option<u8> {
+    if row < 0 || col < 0 {
+        return None;
     }
+    arr.get(row as usize)?.get(col as usize).copied()
+}
 
-    res
+/// A word found starting at (`row`, `col`) and running in `DIRECTIONS[direction]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WordMatch {
+    row: usize,
+    col: usize,
+    direction: usize,
 }
 
-fn transpose(arr: &Array) -> Array {
-    let mut t = Array::new();
-    for _ in 0..arr[0].len() {
-        t.push(Vec::new());
+impl WordMatch {
+    fn cells(&self, len: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (dr, dc, _) = DIRECTIONS[self.direction];
+        let (row, col) = (self.row as i64, self.col as i64);
+        (0..len as i64).map(move |i| ((row + i * dr) as usize, (col + i * dc) as usize))
     }
-    for r in arr.iter() {
-        for (j, c) in r.iter().enumerate() {
-            t[j].push(*c);
+}
+
+/// Every occurrence of `word` in any of the 8 directions, each set of cells counted once.
+fn find_word(arr: &Array, word: &[u8]) -> Vec<WordMatch> {
+    let mut matches = Vec::new();
+    let Some(&first) = word.first() else {
+        return matches;
+    };
+    // A palindrome read backwards covers the same cells as read forwards, and a single letter
+    // covers the same cell in every direction.
+    let directions = match word.len() {
+        1 => 1,
+        _ if word.iter().eq(word.iter().rev()) => 4,
+        _ => DIRECTIONS.len(),
+    };
+    for (row, line) in arr.iter().enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            if c != first {
+                continue;
+            }
+            for (direction, (dr, dc, _)) in DIRECTIONS.iter().enumerate().take(directions) {
+                let found = word.iter().enumerate().skip(1).all(|(i, &w)| {
+                    get(arr, row as i64 + i as i64 * dr, col as i64 + i as i64 * dc) == Some(w)
+                });
+                if found {
+                    matches.push(WordMatch {
+                        row,
+                        col,
+                        direction,
+                    });
+                }
+            }
         }
     }
-    t
+    matches
 }
 
-fn reverse_rows(arr: &Array) -> Array {
-    arr.iter().rev().cloned().collect()
+/// A rectangular 2D pattern, '.' cells match anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stencil {
+    rows: usize,
+    cols: usize,
+    /// the non-wildcard cells as (row, col, letter)
+    cells: Vec<(usize, usize, u8)>,
 }
 
-fn search_x(arr: &Array) -> u64 {
-    let pattern1 = b"MAS";
-    let pattern2 = b"SAM";
-    let mut tot = 0;
-    for (k, r) in get_diags(arr).iter().enumerate() {
-        for (o, w) in r.windows(3).enumerate() {
-            if (w == pattern1 || w == pattern2) && analyze_x(k, o, arr) {
-                tot += 1;
-            }
+impl Stencil {
+    /// Parses rows separated by '/', e.g. "M.S/.A./M.S".
+    fn parse(s: &str) -> Result<Self> {
+        let lines = s.split('/').collect::<Vec<&str>>();
+        let cols = lines[0].len();
+        if cols == 0 || lines.iter().any(|l| l.len() != cols) {
+            return Err(AOCError::ParseError(format!(
+                "stencil {s} is not a non-empty rectangle"
+            )));
         }
+        let cells = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(r, l)| l.bytes().enumerate().map(move |(c, b)| (r, c, b)))
+            .filter(|(_, _, b)| *b != b'.')
+            .collect();
+        Ok(Self {
+            rows: lines.len(),
+            cols,
+            cells,
+        })
     }
-    tot
-}
 
-fn analyze_x(diag: usize, offset: usize, arr: &Array) -> bool {
-    let cols = arr[0].len();
-    let actual_diag = diag as i64 - cols as i64 + 1;
-    let x = (offset as i64 - actual_diag.min(0)) as usize;
-    let y = (offset as i64 + actual_diag.max(0)) as usize;
-    let word = [arr[y + 2][x], arr[y + 1][x + 1], arr[y][x + 2]];
-    word == *b"MAS" || word == *b"SAM"
-}
+    /// The stencil turned 90 degrees clockwise.
+    fn rotate(&self) -> Self {
+        let mut cells = self
+            .cells
+            .iter()
+            .map(|&(r, c, b)| (c, self.rows - 1 - r, b))
+            .collect::<Vec<_>>();
+        cells.sort();
+        Self {
+            rows: self.cols,
+            cols: self.rows,
+            cells,
+        }
+    }
 
-fn search(arr: &Array) -> u64 {
-    let pattern1 = b"XMAS";
-    let pattern2 = b"SAMX";
-    let mut tot = 0;
-    for r in arr {
-        for w in r.windows(4) {
-            if w == pattern1 || w == pattern2 {
-                tot += 1;
+    /// The distinct rotations, indexed by quarter turns.
+    fn rotations(&self) -> Vec<(usize, Stencil)> {
+        let mut current = self.clone();
+        current.cells.sort();
+        let mut rotations: Vec<(usize, Stencil)> = Vec::new();
+        for quarter in 0..4 {
+            if rotations.iter().all(|(_, s)| *s != current) {
+                rotations.push((quarter, current.clone()));
             }
+            current = current.rotate();
         }
+        rotations
     }
-    for r in &transpose(arr) {
-        for w in r.windows(4) {
-            if w == pattern1 || w == pattern2 {
-                tot += 1;
-            }
+
+    fn matches_at(&self, arr: &Array, row: usize, col: usize) -> bool {
+        self.cells
+            .iter()
+            .all(|&(r, c, b)| get(arr, (row + r) as i64, (col + c) as i64) == Some(b))
+    }
+}
+
+/// A stencil found with its top left corner at (`row`, `col`) after `rotation` quarter turns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StencilMatch {
+    row: usize,
+    col: usize,
+    rotation: usize,
+}
+
+impl StencilMatch {
+    fn cells(&self, stencil: &Stencil) -> Vec<(usize, usize)> {
+        let mut rotated = stencil.clone();
+        for _ in 0..self.rotation {
+            rotated = rotated.rotate();
         }
+        rotated
+            .cells
+            .iter()
+            .map(|&(r, c, _)| (self.row + r, self.col + c))
+            .collect()
     }
-    for r in &get_diags(arr) {
-        for w in r.windows(4) {
-            if w == pattern1 || w == pattern2 {
-                tot += 1;
+}
+
+/// Every placement of the stencil in any of its rotations.
+fn find_stencil(arr: &Array, stencil: &Stencil) -> Vec<StencilMatch> {
+    let mut matches = Vec::new();
+    for (rotation, rotated) in stencil.rotations() {
+        for row in 0..arr.len() {
+            for col in 0..arr[row].len() {
+                if rotated.matches_at(arr, row, col) {
+                    matches.push(StencilMatch { row, col, rotation });
+                }
             }
         }
     }
-    for r in &get_diags(&reverse_rows(arr)) {
-        for w in r.windows(4) {
-            if w == pattern1 || w == pattern2 {
-                tot += 1;
+    matches
+}
+
+/// The grid with every cell not in the set replaced by '.'.
+struct Highlight<'a>(&'a Array, &'a HashSet<(usize, usize)>);
+
+impl Display for Highlight<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Highlight(arr, cells) = self;
+        for (r, line) in arr.iter().enumerate() {
+            writeln!(f)?;
+            for (c, b) in line.iter().enumerate() {
+                let b = if cells.contains(&(r, c)) { *b } else { b'.' };
+                write!(f, "{}", b as char)?;
             }
         }
+        Ok(())
     }
-
-    tot
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "\
+MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
+
+    fn parse_str(s: &str) -> Array {
+        s.lines().map(|l| l.as_bytes().to_vec()).collect()
+    }
+
     #[test]
-    fn test_t() {
-        let test = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+    fn test_word() {
+        let arr = parse_str(EXAMPLE);
+        assert_eq!(find_word(&arr, b"XMAS").len(), 18);
+        let arr = parse_str("XMASAMX\n.......");
+        let matches = find_word(&arr, b"XMAS");
+        assert_eq!(
+            matches,
+            vec![
+                WordMatch {
+                    row: 0,
+                    col: 0,
+                    direction: 0
+                },
+                WordMatch {
+                    row: 0,
+                    col: 6,
+                    direction: 4
+                }
+            ]
+        );
         assert_eq!(
-            transpose(&test),
-            vec![vec![1, 4, 7], vec![2, 5, 8], vec![3, 6, 9]]
+            matches[1].cells(4).collect::<Vec<_>>(),
+            vec![(0, 6), (0, 5), (0, 4), (0, 3)]
         );
-        let test2 = vec![vec![1, 2, 3], vec![4, 5, 6]];
-        assert_eq!(transpose(&test2), vec![vec![1, 4], vec![2, 5], vec![3, 6]]);
     }
 
     #[test]
-    fn test_diag() {
-        let test = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+    fn test_word_duplicates() {
+        let arr = parse_str("AB\nBA");
+        assert_eq!(find_word(&arr, b"A").len(), 2);
+        let arr = parse_str("ABA\nB..\nA..");
+        assert_eq!(find_word(&arr, b"ABA").len(), 2);
+        assert_eq!(find_word(&arr, b"AB").len(), 4);
+    }
+
+    #[test]
+    fn test_stencil() {
+        let arr = parse_str(EXAMPLE);
+        let stencil = Stencil::parse("M.S/.A./M.S").unwrap();
+        assert_eq!(stencil.rotations().len(), 4);
+        assert_eq!(find_stencil(&arr, &stencil).len(), 9);
+        let arr = parse_str("S.S\n.A.\nM.M");
+        let matches = find_stencil(&arr, &stencil);
         assert_eq!(
-            get_diags(&test),
-            vec![vec![3], vec![2, 6], vec![1, 5, 9], vec![4, 8], vec![7]]
+            matches,
+            vec![StencilMatch {
+                row: 0,
+                col: 0,
+                rotation: 3
+            }]
         );
-        let test2 = vec![vec![1, 2, 3], vec![4, 5, 6]];
         assert_eq!(
-            get_diags(&test2),
-            vec![vec![3], vec![2, 6], vec![1, 5], vec![4]]
+            matches[0].cells(&stencil),
+            vec![(0, 0), (0, 2), (1, 1), (2, 0), (2, 2)]
         );
+        assert_eq!(Stencil::parse("A").unwrap().rotations().len(), 1);
+        assert!(Stencil::parse("AB/C").is_err());
     }
 }
@@ -1,16 +1,42 @@
-use super::{AOCError, Result};
-use core::panic;
-use std::collections::HashMap;
+use super::{AOCError, Params, Result};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io;
-use std::io::BufRead;
 use std::path::PathBuf;
 
 type Data = Vec<Vec<u64>>;
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["order", "violations", "comparator"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
     let (map, records) = parse(data)?;
-    let res = get_sorted_sum(&map, &records);
+    if params.flag("order")? || verbosity > 1 {
+        match map.global_order() {
+            Ok(order) => println!("global order: {:?}", order),
+            Err(e) => println!("no global order: {e}"),
+        }
+    }
+    if params.flag("violations")? {
+        for rec in &records {
+            let violated = map.violations(rec);
+            if !violated.is_empty() {
+                let rules = violated
+                    .iter()
+                    .map(|(a, b)| format!("{a}|{b}"))
+                    .collect::<Vec<String>>();
+                println!("{:?} violates {}", rec, rules.join(", "));
+            }
+        }
+    }
+    let res = if params.flag("comparator")? {
+        get_sorted_sum_by(&map, &records)
+    } else {
+        let (res, skipped) = get_sorted_sum(&map, &records);
+        for (i, e) in skipped {
+            println!("record {i} skipped: {e}");
+        }
+        res
+    };
     println!("{}, {}", res.0, res.1);
     Ok(())
 }
@@ -18,91 +44,167 @@ pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
 #[derive(Debug, Default)]
 struct OrderMap {
     nodes: HashMap<u64, Vec<u64>>,
-    in_degree: HashMap<u64, usize>,
+    rules: HashSet<(u64, u64)>,
 }
 
-fn get_sorted_sum(map: &OrderMap, recs: &Data) -> (u64, u64) {
-    let mut tot = 0;
-    let mut tot2 = 0;
-    for rec in recs {
-        if is_sorted(map, rec) {
-            tot += rec[rec.len() / 2];
-        } else {
-            match sort(map, rec) {
-                Ok(res) => tot2 += res[res.len() / 2],
-                Err(AOCError::SolverError(_)) => continue,
-                _ => panic!("wtf"),
+impl OrderMap {
+    fn successors(&self, node: &u64) -> &[u64] {
+        self.nodes.get(node).map_or(&[], |succ| succ)
+    }
+
+    /// Topological order of the rules restricted to `nodes`.
+    /// Fails with the nodes of a cycle if there is none.
+    fn sort(&self, nodes: &[u64]) -> Result<Vec<u64>> {
+        let nodes = nodes.iter().copied().collect::<HashSet<u64>>();
+        let mut in_degree: HashMap<u64, usize> = nodes.iter().map(|n| (*n, 0)).collect();
+        for node in &nodes {
+            for succ in self.successors(node) {
+                if let Some(d) = in_degree.get_mut(succ) {
+                    *d += 1;
+                }
+            }
+        }
+        let mut zero_stack = in_degree
+            .iter()
+            .filter(|(_, d)| **d == 0)
+            .map(|(n, _)| *n)
+            .collect::<Vec<u64>>();
+        let mut res = Vec::with_capacity(nodes.len());
+        while let Some(next) = zero_stack.pop() {
+            res.push(next);
+            for succ in self.successors(&next) {
+                if let Some(d) = in_degree.get_mut(succ) {
+                    *d -= 1;
+                    if *d == 0 {
+                        zero_stack.push(*succ);
+                    }
+                }
             }
         }
+        if res.len() != nodes.len() {
+            let cycle = self
+                .find_cycle(&nodes)
+                .unwrap_or_default()
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>();
+            return Err(AOCError::SolverError(format!(
+                "cyclic rules: {}",
+                cycle.join(" -> ")
+            )));
+        }
+        Ok(res)
     }
-    (tot, tot2)
-}
 
-fn sort(map: &OrderMap, rec: &[u64]) -> Result<Vec<u64>> {
-    let mut in_degree = build_in_degree(map, rec);
-    let mut zero_stack = Vec::new();
-    for (node, degree) in in_degree.iter() {
-        if *degree == 0 {
-            zero_stack.push(*node);
-        }
+    /// Topological order over every page mentioned in the rules.
+    fn global_order(&self) -> Result<Vec<u64>> {
+        let nodes = self
+            .rules
+            .iter()
+            .flat_map(|(a, b)| [*a, *b])
+            .collect::<HashSet<u64>>()
+            .into_iter()
+            .collect::<Vec<u64>>();
+        self.sort(&nodes)
     }
-    let mut res = Vec::new();
-    while let Some(next) = zero_stack.pop() {
-        res.push(next);
-        for succ in &map.nodes[&next] {
-            in_degree.entry(*succ).and_modify(|succ| *succ -= 1);
-            if let Some(s) = in_degree.get(succ) {
-                if *s == 0 {
-                    zero_stack.push(*succ);
+
+    /// Iterative dfs over the subgraph induced by `nodes`. Returns the first cycle found,
+    /// with its first node repeated at the end.
+    fn find_cycle(&self, nodes: &HashSet<u64>) -> Option<Vec<u64>> {
+        let mut done: HashSet<u64> = HashSet::new();
+        for root in nodes {
+            if done.contains(root) {
+                continue;
+            }
+            let mut path: Vec<(u64, usize)> = vec![(*root, 0)];
+            while let Some((node, child)) = path.last_mut() {
+                let node = *node;
+                let Some(next) = self.successors(&node).get(*child) else {
+                    done.insert(node);
+                    path.pop();
+                    continue;
+                };
+                *child += 1;
+                if !nodes.contains(next) || done.contains(next) {
+                    continue;
+                }
+                if let Some(start) = path.iter().position(|(n, _)| n == next) {
+                    let mut cycle = path[start..].iter().map(|(n, _)| *n).collect::<Vec<u64>>();
+                    cycle.push(*next);
+                    return Some(cycle);
                 }
+                path.push((*next, 0));
             }
         }
+        None
     }
-    if res.len() != rec.len() {
-        return Err(AOCError::SolverError("cyclic graph".into()));
+
+    /// Rules `a|b` with both pages in `rec` where `b` comes before `a`.
+    fn violations(&self, rec: &[u64]) -> Vec<(u64, u64)> {
+        let pos = rec
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (*p, i))
+            .collect::<HashMap<u64, usize>>();
+        let mut violated = self
+            .rules
+            .iter()
+            .filter(|(a, b)| matches!((pos.get(a), pos.get(b)), (Some(a), Some(b)) if b < a))
+            .copied()
+            .collect::<Vec<(u64, u64)>>();
+        violated.sort();
+        violated
     }
 
-    Ok(res)
-}
+    fn is_sorted(&self, rec: &[u64]) -> bool {
+        self.violations(rec).is_empty()
+    }
 
-fn build_in_degree(map: &OrderMap, rec: &[u64]) -> HashMap<u64, usize> {
-    let mut in_degree: HashMap<u64, usize> = HashMap::new();
-    for (k, v) in map.nodes.iter() {
-        if !rec.contains(k) {
-            continue;
-        }
-        in_degree.entry(*k).or_insert(0);
-        for n in v {
-            if !rec.contains(n) {
-                continue;
-            }
-            in_degree
-                .entry(*n)
-                .and_modify(|entry| *entry += 1)
-                .or_insert(1);
+    /// Orders two pages by a direct rule between them. This is only a total order if every pair
+    /// of pages in a record is covered by a rule.
+    fn compare(&self, a: &u64, b: &u64) -> Ordering {
+        if self.rules.contains(&(*a, *b)) {
+            Ordering::Less
+        } else if self.rules.contains(&(*b, *a)) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
         }
     }
-    in_degree
 }
 
-fn is_sorted(map: &OrderMap, rec: &[u64]) -> bool {
-    let mut in_degree: HashMap<u64, usize> = build_in_degree(map, rec);
-    for p in rec {
-        if let Some(d) = in_degree.get(p) {
-            if *d > 0 {
-                return false;
+/// Sums the middle pages of sorted and of re-sorted records. Records whose rules are cyclic
+/// cannot be re-sorted, they are left out of the second sum and returned with their index.
+fn get_sorted_sum(map: &OrderMap, recs: &Data) -> ((u64, u64), Vec<(usize, AOCError)>) {
+    let mut tot = 0;
+    let mut tot2 = 0;
+    let mut skipped = Vec::new();
+    for (i, rec) in recs.iter().enumerate() {
+        if map.is_sorted(rec) {
+            tot += rec[rec.len() / 2];
+        } else {
+            match map.sort(rec) {
+                Ok(res) => tot2 += res[res.len() / 2],
+                Err(e) => skipped.push((i, e)),
             }
         }
-        if let Some(node) = map.nodes.get(p) {
-            for child in node {
-                in_degree
-                    .entry(*child)
-                    .and_modify(|entry| *entry -= 1)
-                    .or_insert(0);
-            }
+    }
+    ((tot, tot2), skipped)
+}
+
+fn get_sorted_sum_by(map: &OrderMap, recs: &Data) -> (u64, u64) {
+    let mut tot = 0;
+    let mut tot2 = 0;
+    for rec in recs {
+        if rec.is_sorted_by(|a, b| map.compare(a, b) != Ordering::Greater) {
+            tot += rec[rec.len() / 2];
+        } else {
+            let mut res = rec.clone();
+            res.sort_by(|a, b| map.compare(a, b));
+            tot2 += res[res.len() / 2];
         }
     }
-    true
+    (tot, tot2)
 }
 
 enum ParseState {
@@ -111,25 +213,25 @@ enum ParseState {
 }
 
 fn parse(data: PathBuf) -> Result<(OrderMap, Data)> {
+    parse_str(&fs::read_to_string(data)?)
+}
+
+fn parse_str(input: &str) -> Result<(OrderMap, Data)> {
     let mut map = OrderMap::default();
     let mut recs = Data::new();
-    let f = fs::File::open(data)?;
-    let mut reader = io::BufReader::new(f);
-    let mut buf = String::new();
     let mut state = ParseState::Order;
-    while reader.read_line(&mut buf)? > 0 {
-        buf.retain(|c| c != '\n');
+    for line in input.lines() {
         match state {
             ParseState::Order => {
-                if !buf.contains('|') {
+                if !line.contains('|') {
                     state = ParseState::Records;
                 } else {
-                    parse_order(&buf, &mut map)?;
+                    parse_order(line, &mut map)?;
                 }
             }
-            ParseState::Records => recs.push(parse_record(&buf)?),
+            ParseState::Records if line.is_empty() => {}
+            ParseState::Records => recs.push(parse_record(line)?),
         }
-        buf.clear();
     }
 
     Ok((map, recs))
@@ -144,15 +246,9 @@ fn parse_order(line: &str, map: &mut OrderMap) -> Result<()> {
         })
         .collect::<Result<Vec<u64>>>()?;
     if let [first, second, ..] = &nums[..] {
-        map.nodes
-            .entry(*first)
-            .and_modify(|entry| entry.push(*second))
-            .or_insert(vec![*second]);
-        map.in_degree.entry(*first).or_insert(0);
-        map.in_degree
-            .entry(*second)
-            .and_modify(|entry| *entry += 1)
-            .or_insert(1);
+        if map.rules.insert((*first, *second)) {
+            map.nodes.entry(*first).or_default().push(*second);
+        }
         Ok(())
     } else {
         Err(AOCError::ParseError(
@@ -171,3 +267,76 @@ fn parse_record(record: &str) -> Result<Vec<u64>> {
         .collect::<Result<Vec<u64>>>()?;
     Ok(rec)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
+    #[test]
+    fn example() {
+        let (map, records) = parse_str(EXAMPLE).unwrap();
+        let (res, skipped) = get_sorted_sum(&map, &records);
+        assert_eq!(res, (143, 123));
+        assert!(skipped.is_empty());
+        assert_eq!(get_sorted_sum_by(&map, &records), (143, 123));
+        assert_eq!(
+            map.global_order().unwrap(),
+            vec![97, 75, 47, 61, 53, 29, 13]
+        );
+    }
+
+    #[test]
+    fn violations() {
+        let (map, records) = parse_str(EXAMPLE).unwrap();
+        assert_eq!(map.violations(&records[3]), vec![(97, 75)]);
+        assert_eq!(map.violations(&records[4]), vec![(29, 13)]);
+        assert!(map.violations(&records[0]).is_empty());
+    }
+
+    #[test]
+    fn cyclic_rules() {
+        let (map, records) = parse_str("1|2\n2|3\n3|1\n4|1\n\n1,2,3\n4,1,5\n").unwrap();
+        assert!(map.global_order().is_err());
+        let nodes = HashSet::from([1, 2, 3, 4]);
+        let cycle = map.find_cycle(&nodes).unwrap();
+        assert_eq!(cycle.first(), cycle.last());
+        let mut members = cycle[1..].to_vec();
+        members.sort();
+        assert_eq!(members, vec![1, 2, 3]);
+        let (res, skipped) = get_sorted_sum(&map, &records);
+        assert_eq!(res, (1, 0));
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, 0);
+    }
+}
@@ -1,19 +1,20 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::fmt::Display;
 use std::fs;
-use std::io;
-use std::io::BufRead;
 use std::path::PathBuf;
+use std::thread;
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
-    let mut field_map = Field::parse(data)?;
-    let loops = field_map.count_loops(verbosity);
-    while field_map.update().is_ok() {
-        if verbosity > 1 {
-            println!("{}", field_map);
-        }
+pub const PARAMS: &[&str] = &["path"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    let field_map = Field::parse(data)?;
+    let mut walked = field_map.clone();
+    let path = walked.walk(verbosity);
+    if params.flag("path")? {
+        println!("{}", walked);
     }
-    let res = field_map.count_visited();
+    let res = walked.count_visited();
+    let loops = field_map.count_loops(&path);
     println!("part1: {}, part2: {}", res, loops);
     Ok(())
 }
@@ -26,6 +27,104 @@ enum FieldState {
     Empty,
 }
 
+/// For each direction and cell, the cell where the guard stops in front of the next obstacle,
+/// or `None` if it walks off the map.
+struct JumpTable {
+    next: [Vec<Option<(usize, usize)>>; 4],
+    width: usize,
+    height: usize,
+}
+
+impl JumpTable {
+    fn new(field: &FieldMap) -> Self {
+        let height = field.len();
+        let width = field[0].len();
+        let mut next: [Vec<Option<(usize, usize)>>; 4] = Default::default();
+        for n in next.iter_mut() {
+            *n = vec![None; height * width];
+        }
+        let is_obstacle = |i: usize, j: usize| field[i][j] == FieldState::Obstacle;
+        for j in 0..width {
+            let mut stop = None;
+            for i in 0..height {
+                if is_obstacle(i, j) {
+                    stop = Some((i + 1, j));
+                } else {
+                    next[GuardState::Up.index()][i * width + j] = stop;
+                }
+            }
+            stop = None;
+            for i in (0..height).rev() {
+                if is_obstacle(i, j) {
+                    stop = i.checked_sub(1).map(|i| (i, j));
+                } else {
+                    next[GuardState::Down.index()][i * width + j] = stop;
+                }
+            }
+        }
+        for i in 0..height {
+            let mut stop = None;
+            for j in 0..width {
+                if is_obstacle(i, j) {
+                    stop = Some((i, j + 1));
+                } else {
+                    next[GuardState::Left.index()][i * width + j] = stop;
+                }
+            }
+            stop = None;
+            for j in (0..width).rev() {
+                if is_obstacle(i, j) {
+                    stop = j.checked_sub(1).map(|j| (i, j));
+                } else {
+                    next[GuardState::Right.index()][i * width + j] = stop;
+                }
+            }
+        }
+        Self {
+            next,
+            width,
+            height,
+        }
+    }
+
+    /// Simulates the guard with an additional obstacle, jumping from turn to turn.
+    fn is_loop(&self, guard: &GuardInfo, obstacle: (usize, usize)) -> bool {
+        const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+        let mut seen = vec![0_u8; self.height * self.width];
+        let (mut pos, mut dir) = (guard.position, guard.state.index());
+        loop {
+            let (di, dj) = DIRECTIONS[dir];
+            let mut stop = self.next[dir][pos.0 * self.width + pos.1];
+            // distance along the walking direction, negative if behind the guard
+            let ahead = |p: (usize, usize)| {
+                (p.0 as isize - pos.0 as isize) * di + (p.1 as isize - pos.1 as isize) * dj
+            };
+            let on_line = if di == 0 {
+                obstacle.0 == pos.0
+            } else {
+                obstacle.1 == pos.1
+            };
+            let dist = ahead(obstacle);
+            if on_line && dist > 0 && stop.is_none_or(|stop| dist <= ahead(stop)) {
+                stop = Some((
+                    (obstacle.0 as isize - di) as usize,
+                    (obstacle.1 as isize - dj) as usize,
+                ));
+            }
+            let Some(stop) = stop else {
+                return false;
+            };
+            let cell = &mut seen[stop.0 * self.width + stop.1];
+            if *cell & (1 << dir) != 0 {
+                return true;
+            }
+            *cell |= 1 << dir;
+            pos = stop;
+            dir = (dir + 1) % 4;
+        }
+    }
+}
+
 impl Display for FieldState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -47,6 +146,17 @@ enum GuardState {
     Right,
 }
 
+impl GuardState {
+    fn index(&self) -> usize {
+        match self {
+            Self::Up => 0,
+            Self::Right => 1,
+            Self::Down => 2,
+            Self::Left => 3,
+        }
+    }
+}
+
 impl Display for GuardState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -75,23 +185,23 @@ struct Field {
 
 impl Field {
     fn parse(data: PathBuf) -> Result<Self> {
+        Self::parse_str(&fs::read_to_string(data)?)
+    }
+
+    fn parse_str(input: &str) -> Result<Self> {
         let mut state = Field::default();
-        let f = fs::File::open(data)?;
-        let mut reader = io::BufReader::new(f);
-        let mut buf = String::new();
-        while reader.read_line(&mut buf)? > 0 {
-            buf.retain(|c| c != '\n');
+        for line in input.lines() {
             state.field.push(
-                buf.chars()
+                line.chars()
                     .map(|c| match c {
                         '.' => Ok(FieldState::Empty),
                         '#' => Ok(FieldState::Obstacle),
                         '>' => {
-                            state.guard.state = GuardState::Left;
+                            state.guard.state = GuardState::Right;
                             Ok(FieldState::Visited)
                         }
                         '<' => {
-                            state.guard.state = GuardState::Right;
+                            state.guard.state = GuardState::Left;
                             Ok(FieldState::Visited)
                         }
                         'v' => {
@@ -106,7 +216,6 @@ impl Field {
                     })
                     .collect::<Result<Vec<FieldState>>>()?,
             );
-            buf.clear();
         }
         for (i, row) in state.field.iter().enumerate() {
             for (j, f) in row.iter().enumerate() {
@@ -177,35 +286,58 @@ impl Field {
         Ok(())
     }
 
-    fn count_loops(&self, verbosity: u8) -> u64 {
-        let mut n_loops = 0;
-        for i in 0..self.field.len() {
-            for j in 0..self.field[0].len() {
-                if self.field[i][j] == FieldState::Empty {
-                    let mut new_map = self.clone();
-                    new_map.field[i][j] = FieldState::Obstacle;
-                    if new_map.is_loop(verbosity) {
-                        n_loops += 1;
-                    }
-                }
+    /// Walks the guard until it leaves the map (or starts looping), marking visited cells.
+    /// Returns every guard state along the way.
+    fn walk(&mut self, verbosity: u8) -> Vec<GuardInfo> {
+        let width = self.field[0].len();
+        let mut seen = vec![0_u8; self.field.len() * width];
+        let mut path = vec![self.guard.clone()];
+        while self.update().is_ok() {
+            if verbosity > 1 {
+                println!("{}", self);
+            }
+            let (i, j) = self.guard.position;
+            let bit = 1 << self.guard.state.index();
+            if seen[i * width + j] & bit != 0 {
+                break;
             }
+            seen[i * width + j] |= bit;
+            path.push(self.guard.clone());
         }
-        n_loops
+        path
     }
 
-    fn is_loop(&mut self, verbosity: u8) -> bool {
-        let mut current_guard = vec![self.guard.clone()];
-        while self.update().is_ok() {
-            if current_guard.contains(&self.guard) {
-                if verbosity > 2 {
-                    println!("loop found");
-                    println!("{}", self);
-                }
-                return true;
+    /// Only cells on the original path can change the guard's route, so only those are tried.
+    fn count_loops(&self, path: &[GuardInfo]) -> usize {
+        let jumps = JumpTable::new(&self.field);
+        let start = self.guard.position;
+        let mut is_candidate = vec![false; jumps.height * jumps.width];
+        let mut candidates = Vec::new();
+        for guard in path {
+            let (i, j) = guard.position;
+            if guard.position != start && !is_candidate[i * jumps.width + j] {
+                is_candidate[i * jumps.width + j] = true;
+                candidates.push(guard.position);
             }
-            current_guard.push(self.guard.clone());
         }
-        false
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = candidates.len().div_ceil(threads).max(1);
+        thread::scope(|s| {
+            candidates
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    s.spawn(|| {
+                        chunk
+                            .iter()
+                            .filter(|obstacle| jumps.is_loop(&self.guard, **obstacle))
+                            .count()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().expect("loop search panicked"))
+                .sum()
+        })
     }
 
     fn count_visited(&self) -> u64 {
@@ -235,3 +367,39 @@ impl Display for Field {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...
+";
+
+    fn solve(input: &str) -> (u64, usize) {
+        let field = Field::parse_str(input).unwrap();
+        let mut walked = field.clone();
+        let path = walked.walk(0);
+        (walked.count_visited(), field.count_loops(&path))
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE), (41, 6));
+    }
+
+    #[test]
+    fn obstacle_after_turn() {
+        // the guard turns twice before walking down, blocking that exit traps it in place
+        assert_eq!(solve(".#.\n#^#\n...\n"), (2, 1));
+    }
+}
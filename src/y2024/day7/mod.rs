@@ -1,10 +1,23 @@
-use super::{AOCError, Result};
+use super::{AOCError, Params, Result};
 use std::{io::Read, path::PathBuf};
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["ops", "show"];
+
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
     let eqs = parse(data)?;
-    let res = solve(&eqs)?;
+    let part1: [&dyn Operator; 2] = [&Add, &Mul];
+    let part2: [&dyn Operator; 3] = [&Add, &Mul, &Concat { base: 10 }];
+    let show = params.flag("show")?;
+    let res = (solve(&eqs, &part1, show), solve(&eqs, &part2, show));
     println!("part1 : {}, part2: {}", res.0, res.1);
+    if let Some(ops) = params.get::<String>("ops")? {
+        let ops = parse_ops(&ops)?;
+        let ops = ops
+            .iter()
+            .map(|op| op.as_ref())
+            .collect::<Vec<&dyn Operator>>();
+        println!("custom: {}", solve(&eqs, &ops, show));
+    }
     Ok(())
 }
 
@@ -14,61 +27,222 @@ struct Equation {
     data: Vec<u64>,
 }
 
-fn is_solvable(eq: &Equation, current_operand: usize, current_res: u64, concat: bool) -> bool {
-    if current_operand == eq.data.len() {
-        return current_res == eq.res;
-    }
-    if current_res > eq.res {
-        return false;
-    }
-    is_solvable(
-        eq,
-        current_operand + 1,
-        current_res + eq.data[current_operand],
-        concat,
-    ) || is_solvable(
-        eq,
-        current_operand + 1,
-        current_res * eq.data[current_operand],
-        concat,
-    ) || (concat
-        && is_solvable(
-            eq,
-            current_operand + 1,
-            (current_res
-                * 10_u64.pow((eq.data[current_operand] as f64).log10().floor() as u32 + 1))
-                + eq.data[current_operand],
-            concat,
-        ))
-}
-
-fn solve(eqs: &[Equation]) -> Result<(u64, u64)> {
-    Ok((
-        eqs.iter()
-            .map(|eq| {
-                if is_solvable(eq, 0, 0, false) {
-                    eq.res
-                } else {
-                    0
-                }
-            })
-            .sum(),
-        eqs.iter()
-            .map(|eq| {
-                if is_solvable(eq, 0, 0, true) {
-                    eq.res
-                } else {
-                    0
+/// Binary operator evaluated left to right. `invert` allows searching backwards from the result,
+/// which prunes most branches early.
+trait Operator {
+    fn symbol(&self) -> String;
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64>;
+    /// The `lhs` for which `apply(lhs, rhs) == res`, if there is exactly one.
+    fn invert(&self, res: u64, rhs: u64) -> Option<u64>;
+    /// Whether `apply(lhs, rhs) == res` holds for every `lhs`, which `invert` can not express.
+    fn absorbs(&self, _res: u64, _rhs: u64) -> bool {
+        false
+    }
+}
+
+struct Add;
+struct Mul;
+struct Sub;
+struct Xor;
+struct Concat {
+    base: u64,
+}
+
+impl Operator for Add {
+    fn symbol(&self) -> String {
+        "+".into()
+    }
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_add(rhs)
+    }
+    fn invert(&self, res: u64, rhs: u64) -> Option<u64> {
+        res.checked_sub(rhs)
+    }
+}
+
+impl Operator for Mul {
+    fn symbol(&self) -> String {
+        "*".into()
+    }
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_mul(rhs)
+    }
+    fn invert(&self, res: u64, rhs: u64) -> Option<u64> {
+        // multiplying by 0 loses lhs, see `absorbs`
+        if rhs == 0 || !res.is_multiple_of(rhs) {
+            return None;
+        }
+        Some(res / rhs)
+    }
+    fn absorbs(&self, res: u64, rhs: u64) -> bool {
+        rhs == 0 && res == 0
+    }
+}
+
+impl Operator for Sub {
+    fn symbol(&self) -> String {
+        "-".into()
+    }
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_sub(rhs)
+    }
+    fn invert(&self, res: u64, rhs: u64) -> Option<u64> {
+        res.checked_add(rhs)
+    }
+}
+
+impl Operator for Xor {
+    fn symbol(&self) -> String {
+        "^".into()
+    }
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        Some(lhs ^ rhs)
+    }
+    fn invert(&self, res: u64, rhs: u64) -> Option<u64> {
+        Some(res ^ rhs)
+    }
+}
+
+impl Concat {
+    /// base^(number of digits of n)
+    fn shift(&self, mut n: u64) -> Option<u64> {
+        let mut shift = self.base;
+        while n >= self.base {
+            n /= self.base;
+            shift = shift.checked_mul(self.base)?;
+        }
+        Some(shift)
+    }
+}
+
+impl Operator for Concat {
+    fn symbol(&self) -> String {
+        if self.base == 10 {
+            "||".into()
+        } else {
+            format!("||{}", self.base)
+        }
+    }
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_mul(self.shift(rhs)?)?.checked_add(rhs)
+    }
+    fn invert(&self, res: u64, rhs: u64) -> Option<u64> {
+        let shift = self.shift(rhs)?;
+        if res % shift != rhs {
+            return None;
+        }
+        Some(res / shift)
+    }
+}
+
+/// Parses a comma separated operator list like `+,*,||,-,^,||2`.
+fn parse_ops(ops: &str) -> Result<Vec<Box<dyn Operator>>> {
+    ops.split(',')
+        .map(|op| -> Result<Box<dyn Operator>> {
+            Ok(match op {
+                "+" => Box::new(Add),
+                "*" => Box::new(Mul),
+                "-" => Box::new(Sub),
+                "^" => Box::new(Xor),
+                "||" => Box::new(Concat { base: 10 }),
+                _ => {
+                    let base = op
+                        .strip_prefix("||")
+                        .and_then(|base| base.parse::<u64>().ok())
+                        .filter(|base| *base >= 2)
+                        .ok_or(AOCError::ParseError(format!("unknown operator {op}")))?;
+                    Box::new(Concat { base })
                 }
             })
-            .sum(),
-    ))
+        })
+        .collect()
+}
+
+/// Searches from the last operand back to the first, returning the operators in evaluation order.
+fn find_ops<'a>(
+    eq: &Equation,
+    ops: &[&'a dyn Operator],
+    current_operand: usize,
+    current_res: u64,
+) -> Option<Vec<&'a dyn Operator>> {
+    if current_operand == 0 {
+        return (current_res == eq.data[0]).then(Vec::new);
+    }
+    ops.iter().find_map(|op| {
+        let rhs = eq.data[current_operand];
+        let mut found = match op.invert(current_res, rhs) {
+            Some(lhs) => find_ops(eq, ops, current_operand - 1, lhs)?,
+            // any value of the operands before works, as long as they evaluate at all
+            None if op.absorbs(current_res, rhs) => {
+                find_any_ops(&eq.data[1..current_operand], ops, eq.data[0])?
+            }
+            None => return None,
+        };
+        found.push(*op);
+        Some(found)
+    })
+}
+
+/// Searches forward for operators that evaluate `acc` followed by `operands` without failing,
+/// whatever the result.
+fn find_any_ops<'a>(
+    operands: &[u64],
+    ops: &[&'a dyn Operator],
+    acc: u64,
+) -> Option<Vec<&'a dyn Operator>> {
+    let Some((first, rest)) = operands.split_first() else {
+        return Some(Vec::new());
+    };
+    ops.iter().find_map(|op| {
+        let mut found = find_any_ops(rest, ops, op.apply(acc, *first)?)?;
+        found.insert(0, *op);
+        Some(found)
+    })
+}
+
+fn solve_equation<'a>(eq: &Equation, ops: &[&'a dyn Operator]) -> Option<Vec<&'a dyn Operator>> {
+    if eq.data.is_empty() {
+        return None;
+    }
+    find_ops(eq, ops, eq.data.len() - 1, eq.res)
+}
+
+/// Evaluates the operands left to right with the given operators.
+fn evaluate(eq: &Equation, ops: &[&dyn Operator]) -> Option<u64> {
+    ops.iter()
+        .zip(&eq.data[1..])
+        .try_fold(eq.data[0], |acc, (op, val)| op.apply(acc, *val))
+}
+
+fn format_solution(eq: &Equation, ops: &[&dyn Operator]) -> String {
+    let mut s = format!("{} = {}", eq.res, eq.data[0]);
+    for (op, val) in ops.iter().zip(&eq.data[1..]) {
+        s.push_str(&format!(" {} {}", op.symbol(), val));
+    }
+    s
+}
+
+fn solve(eqs: &[Equation], ops: &[&dyn Operator], show: bool) -> u64 {
+    eqs.iter()
+        .filter_map(|eq| {
+            let found = solve_equation(eq, ops)?;
+            debug_assert_eq!(evaluate(eq, &found), Some(eq.res));
+            if show {
+                println!("{}", format_solution(eq, &found));
+            }
+            Some(eq.res)
+        })
+        .sum()
 }
 
 fn parse(data: PathBuf) -> Result<Vec<Equation>> {
     let mut f = std::fs::File::open(data)?;
     let mut buf = String::new();
     let _b = f.read_to_string(&mut buf)?;
+    parse_str(&buf)
+}
+
+fn parse_str(buf: &str) -> Result<Vec<Equation>> {
     let eqs = buf
         .lines()
         .filter(|line| !line.is_empty())
@@ -93,3 +267,61 @@ fn parse(data: PathBuf) -> Result<Vec<Equation>> {
 
     Ok(eqs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "190: 10 19\n3267: 81 40 27\n83: 17 5\n156: 15 6\n7290: 6 8 6 15\n161011: 16 10 13\n192: 17 8 14\n21037: 9 7 18 13\n292: 11 6 16 20\n";
+
+    #[test]
+    fn example() {
+        let eqs = parse_str(EXAMPLE).unwrap();
+        assert_eq!(solve(&eqs, &[&Add, &Mul], false), 3749);
+        assert_eq!(
+            solve(&eqs, &[&Add, &Mul, &Concat { base: 10 }], false),
+            11387
+        );
+    }
+
+    #[test]
+    fn operator_sequence() {
+        let eqs = parse_str("7290: 6 8 6 15\n").unwrap();
+        let ops: [&dyn Operator; 3] = [&Add, &Mul, &Concat { base: 10 }];
+        let found = solve_equation(&eqs[0], &ops).unwrap();
+        assert_eq!(format_solution(&eqs[0], &found), "7290 = 6 * 8 || 6 * 15");
+        assert_eq!(evaluate(&eqs[0], &found), Some(7290));
+    }
+
+    #[test]
+    fn multiply_by_zero() {
+        let eqs = parse_str("0: 5 0\n0: 3 4 0\n").unwrap();
+        let ops: [&dyn Operator; 2] = [&Add, &Mul];
+        let found = solve_equation(&eqs[0], &ops).unwrap();
+        assert_eq!(format_solution(&eqs[0], &found), "0 = 5 * 0");
+        let found = solve_equation(&eqs[1], &ops).unwrap();
+        assert_eq!(format_solution(&eqs[1], &found), "0 = 3 + 4 * 0");
+        // 1 - 2 underflows, so the prefix has to be multiplied instead
+        let eqs = parse_str("0: 1 2 0\n").unwrap();
+        let ops: [&dyn Operator; 2] = [&Sub, &Mul];
+        let found = solve_equation(&eqs[0], &ops).unwrap();
+        assert_eq!(format_solution(&eqs[0], &found), "0 = 1 * 2 * 0");
+        assert_eq!(solve(&eqs, &ops, false), 0);
+        let eqs = parse_str("1: 5 0\n").unwrap();
+        assert!(solve_equation(&eqs[0], &[&Add, &Mul]).is_none());
+    }
+
+    #[test]
+    fn custom_operators() {
+        let ops = parse_ops("-,^,||2").unwrap();
+        let ops = ops
+            .iter()
+            .map(|op| op.as_ref())
+            .collect::<Vec<&dyn Operator>>();
+        let eqs = parse_str("13: 3 1 1\n").unwrap();
+        // (3 ||2 1) ^ 1 = 0b111 ^ 1 = 6, 3 ||2 1 ||2 1 = 0b1111 = 15, 3 - 1 ||2 1 = 0b101 = 5
+        assert!(solve_equation(&eqs[0], &ops).is_none());
+        let eqs = parse_str("15: 3 1 1\n").unwrap();
+        assert!(solve_equation(&eqs[0], &ops).is_some());
+    }
+}
@@ -1,77 +1,107 @@
-use super::Result;
-use core::f64;
-use std::collections::HashMap;
+use super::{AOCError, Params, Result};
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::io::Read;
 use std::path::PathBuf;
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
-    let mut grid = Grid::parse(data)?;
-    let res = grid.get_antinodes(false);
-    if verbosity > 2 {
-        println!("part1:");
-        println!("{}\n", grid);
-    }
-    grid.antinodes.clear();
-    let res2 = grid.get_antinodes(true);
-    if verbosity > 2 {
-        println!("part2:");
-        println!("{}\n", grid);
+pub const PARAMS: &[&str] = &["map", "frequencies"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    let grid = Grid::parse(data)?;
+    let show_map = params.flag("map")? || verbosity > 2;
+    let show_frequencies = params.flag("frequencies")?;
+    let part1 = grid.get_antinodes(false);
+    let part2 = grid.get_antinodes(true);
+    for (name, antinodes) in [("part1", &part1), ("part2", &part2)] {
+        if show_map {
+            println!("{name}:");
+            println!("{}\n", Map(&grid, antinodes));
+        }
+        if show_frequencies {
+            println!("{name} antinodes per frequency:");
+            for (frequency, count) in &antinodes.per_frequency {
+                println!("{frequency}: {count}");
+            }
+            println!();
+        }
     }
-    println!("part1: {}, part2: {}", res, res2);
+    println!("part1: {}, part2: {}", part1.len(), part2.len());
     Ok(())
 }
 
-#[derive(Default, Debug, PartialEq, PartialOrd, Clone)]
+/// Grid position, `x` is the column and `y` the row.
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 struct Point {
-    x: f64,
-    y: f64,
+    x: i64,
+    y: i64,
 }
 
-impl Point {
-    fn is_on_line(&self, line: &Line) -> bool {
-        let d = (line.pos.x - self.x) * line.delta.y - (line.pos.y - self.y) * line.delta.x;
-        (-f64::EPSILON..f64::EPSILON).contains(&d)
-    }
-
-    fn distance(&self, other: &Point) -> f64 {
-        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
-    }
-
-    fn is_equal(&self, other: &Point) -> bool {
-        (self.x - other.x).abs() <= f64::EPSILON && (self.y - other.y).abs() <= f64::EPSILON
+impl std::ops::Add for Point {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Point {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
     }
 }
 
-#[derive(Default, PartialEq, PartialOrd, Debug)]
-struct Line {
-    delta: Point,
-    pos: Point,
-}
-
-impl Line {
-    fn from_points(p1: &Point, p2: &Point) -> Self {
-        Self {
-            delta: Point {
-                x: p1.x - p2.x,
-                y: p1.y - p2.y,
-            },
-            pos: p1.clone(),
+impl std::ops::Sub for Point {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Point {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
         }
     }
 }
 
-#[derive(Default, Debug, PartialEq, PartialOrd)]
-struct Antenna {
-    pos: Point,
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
 }
 
 #[derive(Default, Debug)]
 struct Grid {
-    antennas: HashMap<char, Vec<Antenna>>,
+    antennas: BTreeMap<char, Vec<Point>>,
     cols: usize,
     rows: usize,
-    antinodes: Vec<Point>,
+}
+
+/// Antinode positions as a bitset over the grid cells.
+#[derive(Debug)]
+struct Antinodes {
+    cells: Vec<u64>,
+    cols: usize,
+    /// distinct antinodes created by each frequency, these may overlap between frequencies
+    per_frequency: BTreeMap<char, usize>,
+}
+
+impl Antinodes {
+    fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            cells: vec![0; (rows * cols).div_ceil(64)],
+            cols,
+            per_frequency: BTreeMap::new(),
+        }
+    }
+
+    fn idx(&self, p: &Point) -> usize {
+        p.y as usize * self.cols + p.x as usize
+    }
+
+    fn contains(&self, p: &Point) -> bool {
+        let idx = self.idx(p);
+        self.cells[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    fn insert(&mut self, p: &Point) {
+        let idx = self.idx(p);
+        self.cells[idx / 64] |= 1 << (idx % 64);
+    }
+
+    fn len(&self) -> usize {
+        self.cells.iter().map(|w| w.count_ones() as usize).sum()
+    }
 }
 
 impl Grid {
@@ -79,28 +109,24 @@ impl Grid {
         let mut f = std::fs::File::open(data)?;
         let mut buf = String::new();
         let _n = f.read_to_string(&mut buf)?;
+        Self::parse_str(&buf)
+    }
+
+    fn parse_str(buf: &str) -> Result<Self> {
         let rows = buf.lines().count();
-        let cols = buf.lines().next().unwrap().len();
-        let mut antennas: HashMap<char, Vec<Antenna>> = HashMap::new();
+        let cols = buf
+            .lines()
+            .next()
+            .ok_or(AOCError::ParseError("empty grid".into()))?
+            .len();
+        let mut antennas: BTreeMap<char, Vec<Point>> = BTreeMap::new();
         for (i, line) in buf.lines().enumerate() {
             for (j, c) in line.chars().enumerate() {
                 if c != '.' {
-                    antennas
-                        .entry(c)
-                        .and_modify(|entry| {
-                            entry.push(Antenna {
-                                pos: Point {
-                                    x: j as f64,
-                                    y: i as f64,
-                                },
-                            })
-                        })
-                        .or_insert(vec![Antenna {
-                            pos: Point {
-                                x: j as f64,
-                                y: i as f64,
-                            },
-                        }]);
+                    antennas.entry(c).or_default().push(Point {
+                        x: j as i64,
+                        y: i as i64,
+                    });
                 }
             }
         }
@@ -108,74 +134,87 @@ impl Grid {
             antennas,
             cols,
             rows,
-            antinodes: Vec::new(),
         })
     }
 
-    fn get_antinodes(&mut self, resonant_harmonics: bool) -> u32 {
-        let mut tot = 0;
-        for x in 0..self.rows {
-            for y in 0..self.cols {
-                let mut found = false;
-                let p = Point {
-                    x: x as f64,
-                    y: y as f64,
+    fn contains(&self, p: &Point) -> bool {
+        (0..self.cols as i64).contains(&p.x) && (0..self.rows as i64).contains(&p.y)
+    }
+
+    /// Antinodes of every antenna pair. Without harmonics these are the points in line with both
+    /// antennas where one is twice as far away as the other. With harmonics every grid point in
+    /// line counts, reached by stepping the gcd reduced difference.
+    fn pair_antinodes(&self, a: Point, b: Point, resonant_harmonics: bool) -> Vec<Point> {
+        let d = b - a;
+        if !resonant_harmonics {
+            let mut points = vec![a - d, b + d];
+            if d.x % 3 == 0 && d.y % 3 == 0 {
+                let third = Point {
+                    x: d.x / 3,
+                    y: d.y / 3,
                 };
-                for antenna_group in &self.antennas {
-                    if found {
-                        break;
-                    }
-                    for (i, antenna) in antenna_group.1.iter().enumerate() {
-                        if found {
-                            break;
-                        }
-                        if resonant_harmonics
-                            && antenna_group.1.len() > 1
-                            && p.is_equal(&antenna.pos)
-                        {
-                            tot += 1;
-                            found = true;
-                            self.antinodes.push(p.clone());
-                            break;
-                        }
-                        if !resonant_harmonics && p.is_equal(&antenna.pos) {
-                            continue;
-                        }
-                        let line = Line::from_points(&p, &antenna.pos);
-                        let d = p.distance(&antenna.pos);
-                        for other_antenna in antenna_group.1.iter().skip(i + 1) {
-                            if !resonant_harmonics && p.is_equal(&other_antenna.pos) {
-                                continue;
-                            }
-                            if other_antenna.pos.is_on_line(&line) {
-                                let d2 = p.distance(&other_antenna.pos);
-                                if resonant_harmonics
-                                    || (d2.max(d) - 2. * d2.min(d)).abs() <= f64::EPSILON
-                                {
-                                    found = true;
-                                    tot += 1;
-                                    self.antinodes.push(p.clone());
-                                    break;
-                                }
-                            }
-                        }
+                points.extend([a + third, b - third]);
+            }
+            points.retain(|p| self.contains(p));
+            return points;
+        }
+        let g = gcd(d.x, d.y);
+        let step = Point {
+            x: d.x / g,
+            y: d.y / g,
+        };
+        let mut points = Vec::new();
+        let mut p = a;
+        while self.contains(&p) {
+            points.push(p);
+            p = p + step;
+        }
+        p = a - step;
+        while self.contains(&p) {
+            points.push(p);
+            p = p - step;
+        }
+        points
+    }
+
+    fn get_antinodes(&self, resonant_harmonics: bool) -> Antinodes {
+        let mut all = Antinodes::new(self.rows, self.cols);
+        for (frequency, antennas) in &self.antennas {
+            let mut own = Antinodes::new(self.rows, self.cols);
+            for (i, a) in antennas.iter().enumerate() {
+                for b in &antennas[i + 1..] {
+                    for p in self.pair_antinodes(*a, *b, resonant_harmonics) {
+                        own.insert(&p);
+                        all.insert(&p);
                     }
                 }
             }
+            all.per_frequency.insert(*frequency, own.len());
         }
-        tot
+        all
     }
 }
 
-impl Display for Grid {
+/// Renders a grid with its antinodes, antennas take precedence.
+struct Map<'a>(&'a Grid, &'a Antinodes);
+
+impl Display for Map<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut v = vec![vec!['.'; self.cols]; self.rows];
-        for p in &self.antinodes {
-            v[p.y as usize][p.x as usize] = '#';
+        let Map(grid, antinodes) = self;
+        let mut v = vec![vec!['.'; grid.cols]; grid.rows];
+        for (y, row) in v.iter_mut().enumerate() {
+            for (x, c) in row.iter_mut().enumerate() {
+                if antinodes.contains(&Point {
+                    x: x as i64,
+                    y: y as i64,
+                }) {
+                    *c = '#';
+                }
+            }
         }
-        for a in &self.antennas {
-            for a_ in a.1 {
-                v[a_.pos.y as usize][a_.pos.x as usize] = *a.0;
+        for (frequency, antennas) in &grid.antennas {
+            for a in antennas {
+                v[a.y as usize][a.x as usize] = *frequency;
             }
         }
         for r in &v {
@@ -188,3 +227,22 @@ impl Display for Grid {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let grid = Grid::parse_str("............\n........0...\n.....0......\n.......0....\n....0.......\n......A.....\n............\n............\n........A...\n.........A..\n............\n............\n").unwrap();
+        assert_eq!(grid.get_antinodes(false).len(), 14);
+        assert_eq!(grid.get_antinodes(true).len(), 34);
+    }
+
+    #[test]
+    fn inner_antinodes() {
+        let grid = Grid::parse_str(".......\na..a...\n").unwrap();
+        // outer antinode at (6, 1), inner ones at (1, 1) and (2, 1)
+        assert_eq!(grid.get_antinodes(false).len(), 3);
+    }
+}
@@ -1,365 +1,204 @@
-use super::{AOCError, Result};
-use std::{cmp::Ordering, fmt::Display, path::PathBuf};
+use super::{AOCError, Params, Result};
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Display, path::PathBuf};
 
-pub fn _main(data: PathBuf, verbosity: u8) -> Result<()> {
-    println!("part1");
-    let mut fs = FileSystem::parse(&data)?;
-    fs.compact(verbosity);
-    let res = fs.get_checksum();
-    if verbosity > 3 {
+pub const PARAMS: &[&str] = &["map"];
+
+pub fn _main(data: PathBuf, verbosity: u8, params: &Params) -> Result<()> {
+    let disk = Disk::parse(&data)?;
+    let show_map = params.flag("map")? || verbosity > 3;
+    let blocks = disk.compact_blocks();
+    let files = disk.compact_files();
+    if show_map {
+        println!("before:");
+        println!("{}", disk.block_map());
         println!("part1:");
-        println!("{}", fs);
-        println!();
-    }
-    println!("full");
-    let mut fs2 = FileSystem::parse(&data)?;
-    fs2.compact_stable(verbosity);
-    let res2 = fs2.get_checksum();
-    if verbosity > 3 {
-        println!("full:");
-        println!("{}", fs2);
+        println!("{}", blocks);
+        println!("part2:");
+        println!("{}", disk.with_files(files.clone()).block_map());
         println!();
     }
-    println!("part2");
-    let mut fs3 = FileSystem::parse(&data)?;
-    fs3.compact_once_stable(verbosity);
-    if verbosity > 3 {
-        println!("part2: ");
-        println!("{}", fs3);
-        println!();
-    }
-    let res3 = fs3.get_checksum();
-    println!("part1 res: {}, res2: {}, part2 res: {}", res, res2, res3);
+    let res = blocks.checksum();
+    let res2 = checksum(&files);
+    println!("part1 res: {}, part2 res: {}", res, res2);
     Ok(())
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum File {
-    Empty { length: i8 },
-    NonEmpty { length: i8, id: usize },
+/// Contiguous range of blocks on the disk.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Span {
+    start: usize,
+    length: usize,
 }
+
+/// Disk layout as whole files (indexed by id) and the free gaps between them.
 #[derive(Debug)]
-struct FileSystem {
-    files: Vec<File>,
+struct Disk {
+    files: Vec<Span>,
+    gaps: Vec<Span>,
+    size: usize,
 }
 
-impl FileSystem {
+/// Block by block view of a disk, `None` marks a free block.
+#[derive(Debug, PartialEq, Eq)]
+struct BlockMap(Vec<Option<usize>>);
+
+impl Disk {
     fn parse(data: &PathBuf) -> Result<Self> {
         let f = std::fs::read_to_string(data)?;
-        let mut files =
-            f.chars()
-                .filter(|c| *c != '\n')
-                .enumerate()
-                .map(|(i, c)| {
-                    let n =
-                        c.to_digit(10).ok_or(c).map_err(|e| {
-                            AOCError::ParseError(format!("could not parse length, {e}"))
-                        })? as i8;
-                    if i % 2 == 0 {
-                        Ok(File::NonEmpty {
-                            length: n,
-                            id: i / 2,
-                        })
-                    } else {
-                        Ok(File::Empty { length: n })
-                    }
-                })
-                .collect::<Result<Vec<File>>>()?;
-        if let Some(File::NonEmpty { length: _, id: _ }) = files.last() {
-            files.push(File::Empty { length: 0 });
-        }
-
-        Ok(Self { files })
+        Self::parse_str(&f)
     }
 
-    fn compact(&mut self, verbosity: u8) {
-        while !self.is_compact() {
-            if verbosity > 4 {
-                println!("{}", self);
-            }
-            let last_file = self.nth_last_non_empty(0);
-            let first_free = self.nth_empty_with_size(0, 0);
-            let (delta, id, len) = match (self.files.get(last_file), self.files.get(first_free)) {
-                (
-                    Some(File::NonEmpty {
-                        length: last_length,
-                        id: file_id,
-                    }),
-                    Some(File::Empty {
-                        length: free_length,
-                    }),
-                ) => (*free_length - *last_length, *file_id, *last_length),
-                _ => panic!("Unexpected file type during compaction"),
+    fn parse_str(f: &str) -> Result<Self> {
+        let mut disk = Disk {
+            files: Vec::new(),
+            gaps: Vec::new(),
+            size: 0,
+        };
+        for (i, c) in f.trim_end().chars().enumerate() {
+            let length = c
+                .to_digit(10)
+                .ok_or(AOCError::ParseError(format!("could not parse length, {c}")))?
+                as usize;
+            let span = Span {
+                start: disk.size,
+                length,
             };
-            match delta.cmp(&0) {
-                Ordering::Greater => {
-                    if let Some(File::Empty { length }) = self.files.get_mut(first_free) {
-                        *length -= len;
-                    }
-                    if let Some(File::Empty { length }) = self.files.last_mut() {
-                        *length += len;
-                    }
-                    let f = self.files.remove(last_file);
-                    self.files.insert(first_free, f);
-                }
-                Ordering::Equal => {
-                    self.files[first_free] = self.files.remove(last_file);
-                    if let Some(File::Empty { length }) = self.files.last_mut() {
-                        *length += len;
-                    }
-                }
-                Ordering::Less => {
-                    let new_file = File::NonEmpty {
-                        length: len + delta,
-                        id,
-                    };
-                    if let Some(File::Empty { length }) = self.files.last_mut() {
-                        *length += len + delta;
-                    }
-                    if let Some(File::NonEmpty { length, id: _ }) = self.files.get_mut(last_file) {
-                        *length -= len + delta;
-                    }
-                    self.files[first_free] = new_file;
-                }
+            if i % 2 == 0 {
+                disk.files.push(span);
+            } else if length > 0 {
+                disk.gaps.push(span);
             }
+            disk.size += length;
         }
+        Ok(disk)
     }
 
-    fn is_compact(&self) -> bool {
-        let mut last_empty = false;
-        for f in self.files.iter() {
-            match f {
-                File::Empty { length: _ } => {
-                    last_empty = true;
-                }
-                File::NonEmpty { length: _, id: _ } => {
-                    if last_empty {
-                        return false;
-                    }
-                }
-            }
+    fn with_files(&self, files: Vec<Span>) -> Self {
+        Disk {
+            files,
+            gaps: Vec::new(),
+            size: self.size,
         }
-        true
     }
 
-    fn nth_empty_with_size(&self, mut n: usize, size: i8) -> usize {
-        for (i, f) in self.files.iter().enumerate() {
-            match f {
-                File::Empty { length: _ } => {
-                    if n == 0 {
-                        if let Some(File::Empty { length }) =
-                            self.files.get(self.files.len() - (i + 1))
-                        {
-                            if *length < size {
-                                continue;
-                            }
-                        }
-                        return i;
-                    } else {
-                        n -= 1;
-                    }
-                }
-                File::NonEmpty { length: _, id: _ } => {}
-            }
-        }
-        self.files.len() - 1
-    }
-
-    fn nth_last_non_empty(&self, mut n: usize) -> usize {
-        for (i, f) in self.files.iter().rev().enumerate() {
-            match f {
-                File::Empty { length: _ } => {}
-                File::NonEmpty { length: _, id: _ } => {
-                    if n == 0 {
-                        return self.files.len() - (i + 1);
-                    } else {
-                        n -= 1;
-                    }
-                }
-            }
+    fn block_map(&self) -> BlockMap {
+        let mut blocks = vec![None; self.size];
+        for (id, file) in self.files.iter().enumerate() {
+            blocks[file.start..file.start + file.length].fill(Some(id));
         }
-        0
+        BlockMap(blocks)
     }
 
-    fn get_checksum(&self) -> u64 {
-        let mut idx: u64 = 0;
-        let mut tot = 0;
-        for f in self.files.iter() {
-            match f {
-                File::Empty { length: l } => {
-                    idx += *l as u64;
-                }
-                File::NonEmpty { length: l, id: i } => {
-                    let null_idx = idx;
-                    while ((idx - null_idx) as i8) < *l {
-                        tot += idx * *i as u64;
-                        idx += 1;
-                    }
-                }
-            }
-        }
-        tot
-    }
-
-    fn compact_once_stable(&mut self, verbosity: u8) {
-        let mut i = 0;
-        while i < self.files.len() {
-            // get file n
-            if verbosity > 4 {
-                println!("{self}");
+    /// Moves single blocks from the end into the leftmost free block until no gaps remain.
+    fn compact_blocks(&self) -> BlockMap {
+        let BlockMap(mut blocks) = self.block_map();
+        let (mut free, mut last) = (0, blocks.len());
+        loop {
+            while free < blocks.len() && blocks[free].is_some() {
+                free += 1;
             }
-            self.combine_buffers(verbosity);
-            let next_file = self.nth_last_non_empty(i);
-            if next_file == 0 {
-                return;
+            while last > 0 && blocks[last - 1].is_none() {
+                last -= 1;
             }
-            for j in 0..next_file {
-                // find a fitting buffer left of it
-                let current_buf = self.nth_empty_with_size(j, 0);
-                if current_buf >= next_file {
-                    i += 1;
-                    break;
-                }
-                let (delta, len) = match (self.files.get(next_file), self.files.get(current_buf)) {
-                    (
-                        Some(File::NonEmpty {
-                            length: last_length,
-                            id: _file_id,
-                        }),
-                        Some(File::Empty {
-                            length: free_length,
-                        }),
-                    ) => {
-                        if *last_length > *free_length {
-                            // buffer to small
-                            continue;
-                        }
-                        (*free_length - *last_length, *last_length)
-                    }
-                    (None, None) => panic!("both none returned"),
-                    (None, _) => panic!("no file"),
-                    (_, None) => panic!("no buff"),
-                    _ => panic!("Unexpected file type during compaction"),
-                };
-                if delta > 0 {
-                    // buffer to large -> file gets moved here and a new buffer gets initialized at the old file position
-                    if let Some(File::Empty { length }) = self.files.get_mut(current_buf) {
-                        *length -= len;
-                    }
-                    let f = self.files.remove(next_file);
-                    self.files.insert(current_buf, f);
-                    self.files.insert(next_file, File::Empty { length: len });
-                    break;
-                } else {
-                    // the buffer gets filled completely
-                    self.files[current_buf] = self.files.remove(next_file);
-                    self.files.insert(next_file, File::Empty { length: len });
-                    break;
-                }
+            if last == 0 || free >= last - 1 {
+                break;
             }
+            blocks.swap(free, last - 1);
         }
+        BlockMap(blocks)
     }
 
-    fn combine_buffers(&mut self, verbosity: u8) {
-        let mut i = 0;
-        while i < self.files.len() - 1 {
-            if verbosity > 6 {
-                println!(" w: {}", self);
-            }
-            let l1 = if let Some(File::Empty { length: l1 }) = self.files.get(i) {
-                *l1
-            } else {
-                i += 1;
+    /// Moves every file once, in order of decreasing id, into the leftmost gap that fits it.
+    /// Gaps are kept in one min-heap of start positions per gap length.
+    fn compact_files(&self) -> Vec<Span> {
+        let mut gaps: [BinaryHeap<Reverse<usize>>; 10] = Default::default();
+        for gap in &self.gaps {
+            gaps[gap.length.min(9)].push(Reverse(gap.start));
+        }
+        let mut files = self.files.clone();
+        for file in files.iter_mut().rev() {
+            let Some((length, Reverse(start))) = (file.length..gaps.len())
+                .filter_map(|length| gaps[length].peek().map(|start| (length, *start)))
+                .min_by_key(|(_, Reverse(start))| *start)
+            else {
                 continue;
             };
-            if let Some(File::Empty { length: l2 }) = self.files.get_mut(i + 1) {
-                *l2 += l1;
-            } else {
-                i += 1;
+            if start >= file.start {
                 continue;
             }
-            self.files.remove(i);
+            gaps[length].pop();
+            file.start = start;
+            if length > file.length {
+                gaps[length - file.length].push(Reverse(start + file.length));
+            }
         }
+        files
     }
+}
 
-    fn compact_stable(&mut self, verbosity: u8) {
-        let mut n: usize = 0;
-        let mut size = 0;
-        loop {
-            self.combine_buffers(verbosity);
-            if verbosity > 5 {
-                println!("{}", self);
-            }
-            let current_buf = self.nth_empty_with_size(n, size);
-            if current_buf == self.files.len() - 1 {
-                break;
-            }
-            for i in 0..self.files.len() {
-                let next_file = self.nth_last_non_empty(i);
-                if next_file <= current_buf {
-                    n += 1;
-                    size += 1;
-                    break;
-                }
-
-                let (delta, len) = match (self.files.get(next_file), self.files.get(current_buf)) {
-                    (
-                        Some(File::NonEmpty {
-                            length: last_length,
-                            id: _file_id,
-                        }),
-                        Some(File::Empty {
-                            length: free_length,
-                        }),
-                    ) => {
-                        if *last_length > *free_length {
-                            continue;
-                        }
-                        (*free_length - *last_length, *last_length)
-                    }
-                    (None, None) => panic!("both none returned"),
-                    (None, _) => panic!("no file"),
-                    (_, None) => panic!("no buff"),
-                    _ => panic!("Unexpected file type during compaction"),
-                };
-                if delta > 0 {
-                    if let Some(File::Empty { length }) = self.files.get_mut(current_buf) {
-                        *length -= len;
-                    }
-                    if let Some(File::Empty { length }) = self.files.get_mut(next_file) {
-                        *length += len;
-                    }
-                    let f = self.files.remove(next_file);
-                    self.files.insert(current_buf, f);
-                    break;
-                } else {
-                    self.files[current_buf] = self.files.remove(next_file);
-                    if let Some(File::Empty { length }) = self.files.get_mut(next_file) {
-                        *length += len;
-                    }
-                    break;
-                }
-            }
-        }
+impl BlockMap {
+    fn checksum(&self) -> u64 {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| id.map(|id| (i * id) as u64))
+            .sum()
     }
 }
 
-impl Display for FileSystem {
+fn checksum(files: &[Span]) -> u64 {
+    files
+        .iter()
+        .enumerate()
+        .map(|(id, file)| {
+            let positions =
+                file.length * file.start + file.length * file.length.saturating_sub(1) / 2;
+            (id * positions) as u64
+        })
+        .sum()
+}
+
+impl Display for BlockMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for file in self.files.iter() {
-            match file {
-                File::Empty { length: l } => {
-                    for _ in 0..*l {
-                        write!(f, ".")?;
-                    }
-                }
-                File::NonEmpty { length: l, id: i } => {
-                    for _ in 0..*l {
-                        write!(f, "{i}")?;
-                    }
-                }
+        for block in self.0.iter() {
+            match block {
+                Some(id) => write!(f, "{id}")?,
+                None => write!(f, ".")?,
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2333133121414131402\n";
+
+    #[test]
+    fn block_compaction() {
+        let disk = Disk::parse_str(EXAMPLE).unwrap();
+        let blocks = disk.compact_blocks();
+        assert_eq!(
+            blocks.to_string(),
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(blocks.checksum(), 1928);
+    }
+
+    #[test]
+    fn file_compaction() {
+        let disk = Disk::parse_str(EXAMPLE).unwrap();
+        let files = disk.compact_files();
+        assert_eq!(
+            disk.with_files(files.clone()).block_map().to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+        assert_eq!(checksum(&files), 2858);
+        assert_eq!(
+            checksum(&files),
+            disk.with_files(files).block_map().checksum()
+        );
+    }
+}
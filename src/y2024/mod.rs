@@ -24,4 +24,4 @@ pub mod day7;
 pub mod day8;
 pub mod day9;
 
-pub use super::{AOCError, Result};
+pub use super::{AOCError, Params, Result};
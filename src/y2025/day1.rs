@@ -3,45 +3,92 @@ use std::{
     path::PathBuf,
 };
 
-use crate::{AOCError, Result};
+use crate::{AOCError, Params, Result};
 
-pub fn _main(data: PathBuf, _verbosity: u8) -> Result<()> {
+pub const PARAMS: &[&str] = &["size", "start", "target", "trace"];
+
+pub fn _main(data: PathBuf, _verbosity: u8, params: &Params) -> Result<()> {
+    let dial = Dial::new(
+        params.get_or("size", 100)?,
+        params.get_or("start", 50)?,
+        params.get_or("target", 0)?,
+    )?;
     let ops = parse(data)?;
-    let res1 = part1(&ops);
-    let res2 = part2(&ops);
+    if params.flag("trace")? {
+        trace(&dial, &ops);
+    }
+    let res1 = part1(&dial, &ops);
+    let res2 = part2(&dial, &ops);
     println!("res1: {res1}");
     println!("res2: {res2}");
     Ok(())
 }
 
-fn part2(ops: &[i16]) -> u32 {
-    let mut pos = 50;
+/// A circular dial with positions `0..size`.
+#[derive(Debug, Clone, Copy)]
+struct Dial {
+    size: u16,
+    start: u16,
+    target: u16,
+}
+
+impl Dial {
+    fn new(size: u16, start: u16, target: u16) -> Result<Self> {
+        if size == 0 || start >= size || target >= size {
+            return Err(AOCError::ParseError(format!(
+                "start {start} and target {target} must be positions on a dial of size {size}"
+            )));
+        }
+        Ok(Self {
+            size,
+            start,
+            target,
+        })
+    }
+}
+
+fn trace(dial: &Dial, ops: &[i16]) {
+    let mut pos = dial.start;
+    for (i, op) in ops.iter().enumerate() {
+        let (pos_, hits) = rotate(dial, pos, *op);
+        if hits > 0 {
+            println!(
+                "op {i} ({op:+}): {pos} -> {pos_}, reached {} x{hits}",
+                dial.target
+            );
+        }
+        pos = pos_;
+    }
+}
+
+fn part2(dial: &Dial, ops: &[i16]) -> u32 {
+    let mut pos = dial.start;
     let mut zeros = 0;
     for op in ops {
-        let (pos_, zeroes_) = rotate(pos, *op);
+        let (pos_, zeroes_) = rotate(dial, pos, *op);
         pos = pos_;
-        zeros += zeroes_ as u32;
+        zeros += zeroes_;
     }
     zeros
 }
 
-fn part1(ops: &[i16]) -> u32 {
-    let mut pos = 50;
+fn part1(dial: &Dial, ops: &[i16]) -> u32 {
+    let mut pos = dial.start;
     let mut zeros = 0;
     for op in ops {
-        (pos, _) = rotate(pos, *op);
-        if pos == 0 {
+        (pos, _) = rotate(dial, pos, *op);
+        if pos == dial.target {
             zeros += 1;
         }
     }
     zeros
 }
 
-fn _rotate_brute_force(mut pos: u16, mut by: i16) -> (u16, u16) {
+fn _rotate_brute_force(dial: &Dial, mut pos: u16, mut by: i16) -> (u16, u32) {
     let mut zeroes = 0;
     while by != 0 {
-        pos = ((pos as i16 + by.signum()).rem_euclid(100)) as u16;
-        if pos == 0 {
+        pos = ((pos as i32 + by.signum() as i32).rem_euclid(dial.size as i32)) as u16;
+        if pos == dial.target {
             zeroes += 1;
         }
         by -= by.signum();
@@ -49,19 +96,23 @@ fn _rotate_brute_force(mut pos: u16, mut by: i16) -> (u16, u16) {
     (pos, zeroes)
 }
 
-fn rotate(pos: u16, by: i16) -> (u16, i32) {
-    let from = pos as i32;
+/// New position and how often the dial lands on the target while turning `by` clicks.
+fn rotate(dial: &Dial, pos: u16, by: i16) -> (u16, u32) {
+    let size = dial.size as i32;
+    // measured from the target, so the target sits at 0
+    let from = (pos as i32 - dial.target as i32).rem_euclid(size);
     let to = from + by as i32;
 
     let laps = if by < 0 {
-        (to - 1).div_euclid(100) - (from - 1).div_euclid(100) // if pos was 0, we need to subtract 1
+        (to - 1).div_euclid(size) - (from - 1).div_euclid(size) // if pos was 0, we need to subtract 1
     } else if by > 0 {
-        to / 100
+        to / size
     } else {
         0
     }
-    .abs();
-    (to.rem_euclid(100) as u16, laps)
+    .unsigned_abs();
+    let pos = (to + dial.target as i32).rem_euclid(size) as u16;
+    (pos, laps)
 }
 
 fn parse(data: PathBuf) -> Result<Vec<i16>> {
@@ -84,3 +135,64 @@ fn parse(data: PathBuf) -> Result<Vec<i16>> {
     }
     Ok(ops)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64, good enough to generate test cases without extra dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    #[test]
+    fn example() {
+        let dial = Dial::new(100, 50, 0).unwrap();
+        let ops = [-68, -30, 48, -5, 60, -55, -1, -99, 14, -82];
+        assert_eq!(part1(&dial, &ops), 3);
+        assert_eq!(part2(&dial, &ops), 6);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for case in 0..500 {
+            let size = 1 + rng.below(200) as u16;
+            let dial = Dial::new(
+                size,
+                rng.below(size as u64) as u16,
+                rng.below(size as u64) as u16,
+            )
+            .unwrap();
+            let mut pos = dial.start;
+            for i in 0..50 {
+                let op = rng.below(2001) as i16 - 1000;
+                let expected = _rotate_brute_force(&dial, pos, op);
+                let actual = rotate(&dial, pos, op);
+                assert_eq!(
+                    actual, expected,
+                    "case {case}, op {i}: {dial:?} at {pos} turning {op}"
+                );
+                pos = actual.0;
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_dial() {
+        assert!(Dial::new(0, 0, 0).is_err());
+        assert!(Dial::new(10, 10, 0).is_err());
+        assert!(Dial::new(10, 0, 12).is_err());
+    }
+}
@@ -24,11 +24,7 @@ impl IDRange {
     fn compute_invalids(&self) -> Vec<u64> {
         let mut invalids = Vec::new();
         let mut iter = self.from..=self.to;
-        let iter_ref = &mut iter;
-        loop {
-            let Some(item) = iter_ref.next() else {
-                break;
-            };
+        while let Some(item) = iter.next() {
             let shift = item.ilog10();
             if shift % 2 != 0 {
                 let div = 10_u64.pow(shift.div_ceil(2));
@@ -37,7 +33,7 @@ impl IDRange {
 
                 if upper == lower {
                     invalids.push(item);
-                    _ = iter_ref.nth(div.saturating_sub(1) as usize);
+                    _ = iter.nth(div.saturating_sub(1) as usize);
                 }
             }
         }
@@ -46,10 +42,8 @@ impl IDRange {
     }
 
     fn compute_invalid_part2(&self) -> u64 {
-        let mut iter = self.from..=self.to;
-        let iter_ref = &mut iter;
         let mut sum = 0;
-        while let Some(item) = iter_ref.next() {
+        for item in self.from..=self.to {
             let max_shift = item.ilog10() + 1;
             'shifts: for shift in 1..=(max_shift / 2) {
                 if max_shift % shift != 0 {